@@ -4,13 +4,22 @@
 use crate::params::PUBLIC_PARAMS;
 use crate::{
     keys::{PublicKey, SecretKey},
+    nonce::HedgedNonces,
     prover_server::Proof,
     ticket::Ticket,
-    utils::non_zero_scalar,
+    utils::{ristretto_bytes, ristretto_from_bytes, scalar_bytes, scalar_from_bytes},
 };
 use curve25519_dalek_ng::{ristretto::RistrettoPoint, scalar::Scalar};
 use rand_core::{CryptoRng, RngCore}; // G, H
 
+// What the issuer embeds in a signature. A poisoned signature carries the bit
+// value 2: its proof verifies like any other, but its token fails redemption.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PrivateMetadata {
+    Bit(bool),
+    Poison,
+}
+
 pub struct BlindSignature {
     pub u_big: RistrettoPoint,
     pub v_big: RistrettoPoint,
@@ -29,14 +38,41 @@ impl BlindSignature {
     where
         R: RngCore + CryptoRng,
     {
-        let ts = non_zero_scalar(rng);
-        let d = non_zero_scalar(rng);
+        BlindSignature::create_with_metadata(rng, pk, sk, t, PrivateMetadata::Bit(b))
+    }
 
-        let u_big = &d * &PUBLIC_PARAMS.g_big;
+    pub fn create_with_metadata<R>(
+        rng: &mut R,
+        pk: &PublicKey,
+        sk: &SecretKey,
+        t: &Ticket,
+        metadata: PrivateMetadata,
+    ) -> BlindSignature
+    where
+        R: RngCore + CryptoRng,
+    {
+        let scalar = match metadata {
+            PrivateMetadata::Bit(b) => Scalar::from(b as u64),
+            PrivateMetadata::Poison => Scalar::from(2u64),
+        };
 
-        let mut bytes = [0u8; 32];
-        bytes[0] = b.into();
-        let scalar = Scalar::from_bytes_mod_order(bytes);
+        // ts, d <-- hedged from sk, the request and fresh randomness
+        let mut nonces = HedgedNonces::new(
+            rng,
+            b"MacTok-BlindSignature",
+            sk,
+            &[&scalar],
+            &[
+                &ristretto_bytes(&pk.z_big),
+                &ristretto_bytes(&pk.c_big_x),
+                &ristretto_bytes(&pk.c_big_y),
+                &t.to_bytes(),
+            ],
+        );
+        let ts = nonces.non_zero_scalar();
+        let d = nonces.non_zero_scalar();
+
+        let u_big = &d * &PUBLIC_PARAMS.g_big;
 
         let v_big = (&sk.x_big + (&scalar * &sk.y_big) + (&ts * &pk.z_big) + t.t_big) * &d;
 
@@ -52,4 +88,29 @@ impl BlindSignature {
         };
         blind_signature
     }
+
+    pub const SIZE: usize = 32 * 3 + Proof::SIZE;
+
+    // U || V || ts || pi
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(BlindSignature::SIZE);
+        bytes.extend_from_slice(&ristretto_bytes(&self.u_big));
+        bytes.extend_from_slice(&ristretto_bytes(&self.v_big));
+        bytes.extend_from_slice(&scalar_bytes(&self.ts));
+        bytes.extend_from_slice(&self.pi.to_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<BlindSignature, ()> {
+        if bytes.len() != BlindSignature::SIZE {
+            return Err(());
+        }
+
+        Ok(BlindSignature {
+            u_big: ristretto_from_bytes(&bytes[..32])?,
+            v_big: ristretto_from_bytes(&bytes[32..64])?,
+            ts: scalar_from_bytes(&bytes[64..96])?,
+            pi: Proof::from_bytes(&bytes[96..])?,
+        })
+    }
 }
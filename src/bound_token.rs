@@ -0,0 +1,61 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use curve25519_dalek_ng::{ristretto::RistrettoPoint, scalar::Scalar};
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha512;
+
+use crate::{token::Token, utils::ristretto_bytes, utils::scalar_bytes};
+
+type HmacSha512 = Hmac<Sha512>;
+
+const BINDING_LABEL: &[u8] = b"MacTok-BoundToken";
+
+// A token presentation bound to a message, e.g. a request digest or a TLS
+// exporter value. Q is never sent; it keys a MAC over the message instead, so
+// the presentation cannot be replayed against a different message.
+pub struct BoundToken {
+    pub t: Scalar,
+    pub p_big: RistrettoPoint,
+    pub mac: [u8; 64],
+}
+
+impl BoundToken {
+    pub fn create(token: &Token, message: &[u8]) -> BoundToken {
+        let mac: [u8; 64] = binding_mac(&token.q_big, &token.t, &token.p_big, message)
+            .finalize()
+            .into_bytes()
+            .as_slice()
+            .try_into()
+            .expect("incorrect size for mac");
+
+        BoundToken {
+            t: token.t,
+            p_big: token.p_big,
+            mac,
+        }
+    }
+
+    // Constant-time check of the binding against a candidate Q
+    pub(crate) fn verify(&self, q_big: &RistrettoPoint, message: &[u8]) -> bool {
+        binding_mac(q_big, &self.t, &self.p_big, message)
+            .verify(&self.mac)
+            .is_ok()
+    }
+}
+
+fn binding_mac(
+    q_big: &RistrettoPoint,
+    t: &Scalar,
+    p_big: &RistrettoPoint,
+    message: &[u8],
+) -> HmacSha512 {
+    let mut mac =
+        HmacSha512::new_from_slice(&ristretto_bytes(q_big)).expect("HMAC accepts any key size");
+    mac.update(BINDING_LABEL);
+    mac.update(&scalar_bytes(t));
+    mac.update(&ristretto_bytes(p_big));
+    mac.update(&(message.len() as u64).to_be_bytes());
+    mac.update(message);
+    mac
+}
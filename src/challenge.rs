@@ -0,0 +1,155 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use std::collections::HashMap;
+
+use curve25519_dalek_ng::scalar::Scalar;
+use rand_core::{CryptoRng, RngCore};
+use sha2::{Digest, Sha256};
+
+use crate::{bound_token::BoundToken, keys::KeyId, spent_tokens::SpentTokens, token::Token};
+
+// Issued by an origin; the client binds its token to the challenge when presenting it
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenChallenge {
+    pub key_id: KeyId,
+    pub origin: String,
+    pub nonce: Option<[u8; 32]>,
+}
+
+pub struct ChallengeToken {
+    pub challenge_digest: [u8; 32],
+    pub token: BoundToken,
+}
+
+// The challenges an origin has handed out and will accept tokens for, until
+// lifetime has passed since they were issued
+pub struct ChallengeRegistry {
+    pub origin: String,
+    pub lifetime: u64,
+    issued: HashMap<[u8; 32], IssuedChallenge>,
+}
+
+struct IssuedChallenge {
+    challenge: TokenChallenge,
+    expires: u64,
+    // Tokens redeemed against a challenge without a nonce
+    spent: SpentTokens,
+}
+
+impl TokenChallenge {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let origin = self.origin.as_bytes();
+        let mut bytes = Vec::with_capacity(32 + 2 + origin.len() + 1 + 32);
+        bytes.extend_from_slice(&self.key_id);
+        bytes.extend_from_slice(&(origin.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(origin);
+        match &self.nonce {
+            Some(nonce) => {
+                bytes.push(1);
+                bytes.extend_from_slice(nonce);
+            }
+            None => bytes.push(0),
+        }
+        bytes
+    }
+
+    pub fn digest(&self) -> [u8; 32] {
+        Sha256::digest(&self.to_bytes()).into()
+    }
+}
+
+impl ChallengeToken {
+    pub fn create(token: &Token, challenge: &TokenChallenge) -> ChallengeToken {
+        ChallengeToken {
+            challenge_digest: challenge.digest(),
+            token: BoundToken::create(token, &challenge.to_bytes()),
+        }
+    }
+}
+
+impl ChallengeRegistry {
+    pub fn new(origin: &str, lifetime: u64) -> ChallengeRegistry {
+        assert!(origin.len() <= u16::MAX as usize, "origin name too long");
+
+        ChallengeRegistry {
+            origin: origin.to_string(),
+            lifetime,
+            issued: HashMap::new(),
+        }
+    }
+
+    // A challenge without a nonce accepts any number of distinct tokens until
+    // it expires; one with a nonce is consumed by its first redemption.
+    // Expired challenges are dropped on every call.
+    pub fn issue<R>(
+        &mut self,
+        rng: &mut R,
+        key_id: KeyId,
+        with_nonce: bool,
+        now: u64,
+    ) -> TokenChallenge
+    where
+        R: RngCore + CryptoRng,
+    {
+        self.prune(now);
+
+        let nonce = if with_nonce {
+            let mut nonce = [0u8; 32];
+            rng.fill_bytes(&mut nonce);
+            Some(nonce)
+        } else {
+            None
+        };
+
+        let challenge = TokenChallenge {
+            key_id,
+            origin: self.origin.clone(),
+            nonce,
+        };
+        self.issued.insert(
+            challenge.digest(),
+            IssuedChallenge {
+                challenge: challenge.clone(),
+                expires: now.saturating_add(self.lifetime),
+                spent: SpentTokens::new(),
+            },
+        );
+        challenge
+    }
+
+    // None for unknown and expired challenges
+    pub fn get(&self, digest: &[u8; 32], now: u64) -> Option<&TokenChallenge> {
+        self.issued
+            .get(digest)
+            .filter(|issued| now < issued.expires)
+            .map(|issued| &issued.challenge)
+    }
+
+    pub fn len(&self) -> usize {
+        self.issued.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.issued.is_empty()
+    }
+
+    // Drops the challenges that have expired by now
+    pub fn prune(&mut self, now: u64) {
+        self.issued.retain(|_, issued| now < issued.expires);
+    }
+
+    // Records a redemption of t against the challenge. Fails if the challenge
+    // is gone or t was already redeemed against it.
+    pub(crate) fn consume(&mut self, digest: &[u8; 32], t: &Scalar) -> Result<(), ()> {
+        let issued = self.issued.get_mut(digest).ok_or(())?;
+        if issued.challenge.nonce.is_some() {
+            self.issued.remove(digest);
+            return Ok(());
+        }
+        if !issued.spent.mark_spent(t) {
+            return Err(());
+        }
+        Ok(())
+    }
+}
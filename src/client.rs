@@ -0,0 +1,72 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use rand_core::{CryptoRng, RngCore};
+
+use crate::{
+    blind_sig::BlindSignature,
+    bound_token::BoundToken,
+    challenge::{ChallengeToken, TokenChallenge},
+    keys::PublicKey,
+    ticket::{Receipt, Ticket},
+    token::Token,
+};
+
+// Client side of issuance and redemption as a chain of states, each consumed
+// by the step that leaves it. A pending issuance can be finalized once, and
+// the token it yields can be presented once; both steps take self, so a
+// second call does not compile.
+pub struct PendingIssuance {
+    ticket: Ticket,
+    receipt: Receipt,
+}
+
+// A finalized token that has not been presented yet
+pub struct UnspentToken {
+    token: Token,
+}
+
+impl PendingIssuance {
+    pub fn create<R>(rng: &mut R, pk: &PublicKey) -> PendingIssuance
+    where
+        R: RngCore + CryptoRng,
+    {
+        let (ticket, receipt) = Ticket::create(rng, pk);
+        PendingIssuance { ticket, receipt }
+    }
+
+    // The ticket to send to the issuer
+    pub fn ticket(&self) -> &Ticket {
+        &self.ticket
+    }
+
+    // The receipt is dropped either way; a rejected signature means starting
+    // over with a fresh ticket
+    pub fn finalize<R>(
+        self,
+        rng: &mut R,
+        pk: &PublicKey,
+        bs: &BlindSignature,
+    ) -> Result<UnspentToken, ()>
+    where
+        R: RngCore + CryptoRng,
+    {
+        let token = Token::create(rng, pk, bs, &self.ticket, &self.receipt)?;
+        Ok(UnspentToken { token })
+    }
+}
+
+// The token itself is only reachable by presenting it
+impl UnspentToken {
+    pub fn present(self) -> Token {
+        self.token
+    }
+
+    pub fn present_bound(self, message: &[u8]) -> BoundToken {
+        BoundToken::create(&self.token, message)
+    }
+
+    pub fn present_to_challenge(self, challenge: &TokenChallenge) -> ChallengeToken {
+        ChallengeToken::create(&self.token, challenge)
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use curve25519_dalek_ng::{ristretto::RistrettoPoint, scalar::Scalar};
+use rand_core::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+
+use crate::{
+    dleq::KnowledgeProof,
+    keys::PublicKey,
+    params::PUBLIC_PARAMS,
+    ticket::{Receipt, Ticket},
+    token::Token,
+    utils::{non_zero_scalar, ristretto_bytes, scalar_bytes},
+};
+
+// Issuer key for client-bound tickets: a ticket T = tc * Z + r * G + k * W
+// leaves k * w * P in the token's Q, which only the holder of k can account for
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct BindingSecretKey {
+    pub w: Scalar,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct BindingPublicKey {
+    pub w_big: RistrettoPoint,
+}
+
+// Long-term client key pair, K = k * G
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ClientKey {
+    pub k: Scalar,
+    pub k_big: RistrettoPoint,
+}
+
+// A token presented together with a proof of possession of the client key it
+// was issued to. K_P = k * P; the proof shows knowledge of log_P K_P. K itself
+// is never sent, and P is fresh per token, so redemptions stay unlinkable.
+pub struct ClientBoundToken {
+    pub t: Scalar,
+    pub p_big: RistrettoPoint,
+    pub q_big: RistrettoPoint,
+    pub k_big_p: RistrettoPoint,
+    pub pi: KnowledgeProof,
+}
+
+// Proof that a bound ticket T = tc * Z + r * G + k * W uses the k of a
+// registered K = k * G, without revealing tc, r or k
+#[derive(Debug, Clone, PartialEq)]
+pub struct TicketBindingProof {
+    pub e: Scalar,
+    pub a_tc: Scalar,
+    pub a_r: Scalar,
+    pub a_k: Scalar,
+}
+
+impl BindingSecretKey {
+    pub fn create<R>(rng: &mut R) -> BindingSecretKey
+    where
+        R: RngCore + CryptoRng,
+    {
+        BindingSecretKey {
+            w: non_zero_scalar(rng),
+        }
+    }
+}
+
+impl BindingPublicKey {
+    pub fn create(secret_key: &BindingSecretKey) -> BindingPublicKey {
+        BindingPublicKey {
+            w_big: &PUBLIC_PARAMS.g_big * &secret_key.w,
+        }
+    }
+}
+
+impl ClientKey {
+    pub fn create<R>(rng: &mut R) -> ClientKey
+    where
+        R: RngCore + CryptoRng,
+    {
+        let k = non_zero_scalar(rng);
+
+        ClientKey {
+            k,
+            k_big: &PUBLIC_PARAMS.g_big * &k,
+        }
+    }
+}
+
+impl ClientBoundToken {
+    pub fn create<R>(
+        rng: &mut R,
+        token: &Token,
+        client_key: &ClientKey,
+        message: &[u8],
+    ) -> ClientBoundToken
+    where
+        R: RngCore + CryptoRng,
+    {
+        let k_big_p = client_key.k * token.p_big;
+        let context = proof_context(&token.t, &token.q_big, message);
+        let pi = KnowledgeProof::create(rng, &client_key.k, &token.p_big, &k_big_p, &context);
+
+        ClientBoundToken {
+            t: token.t,
+            p_big: token.p_big,
+            q_big: token.q_big,
+            k_big_p,
+            pi,
+        }
+    }
+
+    pub(crate) fn verify_possession(&self, message: &[u8]) -> Result<(), ()> {
+        let context = proof_context(&self.t, &self.q_big, message);
+        self.pi.verify(&self.p_big, &self.k_big_p, &context)
+    }
+}
+
+impl TicketBindingProof {
+    pub fn create<R>(
+        rng: &mut R,
+        pk: &PublicKey,
+        bpk: &BindingPublicKey,
+        client_key: &ClientKey,
+        ticket: &Ticket,
+        receipt: &Receipt,
+    ) -> TicketBindingProof
+    where
+        R: RngCore + CryptoRng,
+    {
+        // r_tc, r_r, r_k <-- ZZ_p
+        let r_tc = Scalar::random(rng);
+        let r_r = Scalar::random(rng);
+        let r_k = Scalar::random(rng);
+
+        // C_t <-- r_tc * Z + r_r * G + r_k * W; C_k <-- r_k * G
+        let c_big_t = (r_tc * pk.z_big) + (&r_r * &PUBLIC_PARAMS.g_big) + (r_k * bpk.w_big);
+        let c_big_k = &r_k * &PUBLIC_PARAMS.g_big;
+
+        let e = binding_challenge(pk, bpk, &client_key.k_big, ticket, &c_big_t, &c_big_k);
+
+        TicketBindingProof {
+            e,
+            a_tc: r_tc + (e * receipt.tc),
+            a_r: r_r + (e * receipt.r),
+            a_k: r_k + (e * client_key.k),
+        }
+    }
+
+    pub fn verify(
+        &self,
+        pk: &PublicKey,
+        bpk: &BindingPublicKey,
+        k_big: &RistrettoPoint,
+        ticket: &Ticket,
+    ) -> Result<(), ()> {
+        // C_t <-- a_tc * Z + a_r * G + a_k * W - e * T; C_k <-- a_k * G - e * K
+        let c_big_t =
+            (self.a_tc * pk.z_big) + (&self.a_r * &PUBLIC_PARAMS.g_big) + (self.a_k * bpk.w_big)
+                - (self.e * ticket.t_big);
+        let c_big_k = (&self.a_k * &PUBLIC_PARAMS.g_big) - (self.e * k_big);
+
+        if binding_challenge(pk, bpk, k_big, ticket, &c_big_t, &c_big_k) != self.e {
+            return Err(());
+        }
+
+        Ok(())
+    }
+}
+
+fn binding_challenge(
+    pk: &PublicKey,
+    bpk: &BindingPublicKey,
+    k_big: &RistrettoPoint,
+    ticket: &Ticket,
+    c_big_t: &RistrettoPoint,
+    c_big_k: &RistrettoPoint,
+) -> Scalar {
+    let mut hasher = Sha512::new();
+    hasher.update(b"MacTok-TicketBinding");
+    for point in [
+        &pk.z_big,
+        &bpk.w_big,
+        k_big,
+        &ticket.t_big,
+        c_big_t,
+        c_big_k,
+    ] {
+        hasher.update(ristretto_bytes(point));
+    }
+
+    Scalar::from_hash(hasher)
+}
+
+// The proof covers the rest of the token and the message it is presented for
+fn proof_context(t: &Scalar, q_big: &RistrettoPoint, message: &[u8]) -> Vec<u8> {
+    let mut context = Vec::with_capacity(64 + message.len());
+    context.extend_from_slice(&scalar_bytes(t));
+    context.extend_from_slice(&ristretto_bytes(q_big));
+    context.extend_from_slice(message);
+    context
+}
@@ -0,0 +1,700 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+// Keyed-verification anonymous credentials over n attributes, generalizing the
+// token MAC (x + t * z) * P to (x + sum m_i * z_i) * U.
+//
+// Issuance: the client commits to its hidden attributes as
+// T = r * G + sum_hidden m_i * Z_i and proves the opening. The issuer returns
+// U = d * G and V = d * (X + T + sum_revealed m_j * Z_j) with a proof against
+// C_x, and the client unblinds W = V - r * U.
+//
+// Presentation: the client randomizes (U, W) to (U', W'), commits to each
+// hidden attribute as C_i = m_i * U' + r_i * G and to W' as C_w = W' + r_w * G.
+// The verifier computes
+//   V' = x * U' + sum_revealed m_j * z_j * U' + sum_hidden z_i * C_i - C_w,
+// which equals sum_hidden r_i * Z_i - r_w * G exactly when the MAC is valid,
+// and checks the client's proof of knowledge of that representation.
+
+use curve25519_dalek_ng::{ristretto::RistrettoPoint, scalar::Scalar, traits::Identity};
+use rand_core::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+
+use crate::{
+    nonce::HedgedNonces,
+    params::PUBLIC_PARAMS,
+    utils::{non_zero_scalar, ristretto_bytes, scalar_bytes},
+};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct CredentialSecretKey {
+    pub x: Scalar,
+    pub r_x: Scalar,
+    // One key per attribute
+    pub z: Vec<Scalar>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct CredentialPublicKey {
+    pub c_big_x: RistrettoPoint,
+    pub z_big: Vec<RistrettoPoint>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Attribute {
+    // Seen by the issuer
+    Revealed(Scalar),
+    // Committed to by the client, never seen by the issuer
+    Hidden(Scalar),
+}
+
+// A statement about a hidden attribute proven in a presentation
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate {
+    OneOf { index: usize, values: Vec<Scalar> },
+}
+
+pub struct OpeningProof {
+    pub e: Scalar,
+    pub a_r: Scalar,
+    // Indexed like the attributes; None for revealed ones
+    pub a_m: Vec<Option<Scalar>>,
+}
+
+pub struct CredentialRequest {
+    pub t_big: RistrettoPoint,
+    pub revealed: Vec<Option<Scalar>>,
+    pub pi: OpeningProof,
+}
+
+pub struct CredentialReceipt {
+    pub r: Scalar,
+    pub attributes: Vec<Scalar>,
+}
+
+pub struct IssuanceProof {
+    pub e: Scalar,
+    pub a_delta: Scalar,
+    pub a_rho: Scalar,
+}
+
+pub struct CredentialResponse {
+    pub u_big: RistrettoPoint,
+    pub v_big: RistrettoPoint,
+    pub pi: IssuanceProof,
+}
+
+pub struct Credential {
+    pub u_big: RistrettoPoint,
+    pub w_big: RistrettoPoint,
+    pub attributes: Vec<Scalar>,
+}
+
+pub struct SetMembershipProof {
+    pub e: Vec<Scalar>,
+    pub a: Vec<Scalar>,
+}
+
+pub struct CredentialPresentation {
+    pub u_big: RistrettoPoint,
+    pub c_big_w: RistrettoPoint,
+    pub revealed: Vec<Option<Scalar>>,
+    // Commitments to the hidden attributes; None for revealed ones
+    pub c_big: Vec<Option<RistrettoPoint>>,
+    pub predicates: Vec<Predicate>,
+    pub e: Scalar,
+    pub a_w: Scalar,
+    pub a_m: Vec<Option<Scalar>>,
+    pub a_r: Vec<Option<Scalar>>,
+    pub set_proofs: Vec<SetMembershipProof>,
+}
+
+// Maps arbitrary attribute bytes (a country code, a tier name) to a scalar
+pub fn attribute_from_bytes(bytes: &[u8]) -> Scalar {
+    let mut hasher = Sha512::new();
+    hasher.update(b"MacTok-CredentialAttribute");
+    hasher.update(bytes);
+
+    Scalar::from_hash(hasher)
+}
+
+impl CredentialSecretKey {
+    pub fn create<R>(rng: &mut R, n: usize) -> CredentialSecretKey
+    where
+        R: RngCore + CryptoRng,
+    {
+        CredentialSecretKey {
+            x: non_zero_scalar(rng),
+            r_x: non_zero_scalar(rng),
+            z: (0..n).map(|_| non_zero_scalar(rng)).collect(),
+        }
+    }
+}
+
+impl CredentialPublicKey {
+    pub fn create(secret_key: &CredentialSecretKey) -> CredentialPublicKey {
+        CredentialPublicKey {
+            c_big_x: (&secret_key.x * &PUBLIC_PARAMS.g_big)
+                + (&secret_key.r_x * &PUBLIC_PARAMS.h_big),
+            z_big: secret_key
+                .z
+                .iter()
+                .map(|z| &PUBLIC_PARAMS.g_big * z)
+                .collect(),
+        }
+    }
+}
+
+impl Attribute {
+    fn value(&self) -> Scalar {
+        match self {
+            Attribute::Revealed(m) | Attribute::Hidden(m) => *m,
+        }
+    }
+}
+
+impl CredentialRequest {
+    pub fn create<R>(
+        rng: &mut R,
+        cpk: &CredentialPublicKey,
+        attributes: &[Attribute],
+    ) -> Result<(CredentialRequest, CredentialReceipt), ()>
+    where
+        R: RngCore + CryptoRng,
+    {
+        if attributes.len() != cpk.z_big.len() {
+            return Err(());
+        }
+
+        // T = r * G + sum_hidden m_i * Z_i
+        let r = non_zero_scalar(rng);
+        let mut t_big = &r * &PUBLIC_PARAMS.g_big;
+        for (attribute, z_big) in attributes.iter().zip(&cpk.z_big) {
+            if let Attribute::Hidden(m) = attribute {
+                t_big += m * z_big;
+            }
+        }
+
+        // Proof of knowledge of the opening of T
+        let k_r = Scalar::random(rng);
+        let mut c_big_t = &k_r * &PUBLIC_PARAMS.g_big;
+        let k_m: Vec<Option<Scalar>> = attributes
+            .iter()
+            .map(|attribute| match attribute {
+                Attribute::Hidden(_) => Some(Scalar::random(rng)),
+                Attribute::Revealed(_) => None,
+            })
+            .collect();
+        for (k, z_big) in k_m.iter().zip(&cpk.z_big) {
+            if let Some(k) = k {
+                c_big_t += k * z_big;
+            }
+        }
+
+        let revealed: Vec<Option<Scalar>> = attributes
+            .iter()
+            .map(|attribute| match attribute {
+                Attribute::Revealed(m) => Some(*m),
+                Attribute::Hidden(_) => None,
+            })
+            .collect();
+
+        let e = request_challenge(cpk, &t_big, &revealed, &c_big_t);
+        let a_r = k_r + (e * r);
+        let a_m = k_m
+            .iter()
+            .zip(attributes)
+            .map(|(k, attribute)| k.map(|k| k + (e * attribute.value())))
+            .collect();
+
+        let request = CredentialRequest {
+            t_big,
+            revealed,
+            pi: OpeningProof { e, a_r, a_m },
+        };
+        let receipt = CredentialReceipt {
+            r,
+            attributes: attributes.iter().map(Attribute::value).collect(),
+        };
+
+        Ok((request, receipt))
+    }
+
+    pub fn verify(&self, cpk: &CredentialPublicKey) -> Result<(), ()> {
+        let n = cpk.z_big.len();
+        if self.revealed.len() != n || self.pi.a_m.len() != n {
+            return Err(());
+        }
+
+        // C_t <-- a_r * G + sum_hidden a_m_i * Z_i - e * T
+        let mut c_big_t = (&self.pi.a_r * &PUBLIC_PARAMS.g_big) - (self.pi.e * self.t_big);
+        for ((revealed, a_m), z_big) in self.revealed.iter().zip(&self.pi.a_m).zip(&cpk.z_big) {
+            match (revealed, a_m) {
+                (None, Some(a_m)) => c_big_t += a_m * z_big,
+                (Some(_), None) => (),
+                _ => return Err(()),
+            }
+        }
+
+        let e_verify = request_challenge(cpk, &self.t_big, &self.revealed, &c_big_t);
+        if e_verify != self.pi.e {
+            return Err(());
+        }
+
+        Ok(())
+    }
+}
+
+impl CredentialResponse {
+    pub fn create<R>(
+        rng: &mut R,
+        csk: &CredentialSecretKey,
+        cpk: &CredentialPublicKey,
+        request: &CredentialRequest,
+    ) -> Result<CredentialResponse, ()>
+    where
+        R: RngCore + CryptoRng,
+    {
+        request.verify(cpk)?;
+
+        // V = d * (X + T'), T' = T + sum_revealed m_j * Z_j
+        let t_big_full = full_ticket(cpk, request);
+        let x_big = &csk.x * &PUBLIC_PARAMS.g_big;
+
+        // d and the proof nonces are hedged from the key, the request and
+        // fresh randomness
+        let secrets: Vec<&Scalar> = [&csk.x, &csk.r_x].into_iter().chain(&csk.z).collect();
+        let mut nonces = HedgedNonces::with_secrets(
+            rng,
+            b"MacTok-CredentialResponse",
+            &secrets,
+            &[&ristretto_bytes(&t_big_full)],
+        );
+
+        let d = nonces.non_zero_scalar();
+        let u_big = &d * &PUBLIC_PARAMS.g_big;
+        let v_big = d * (x_big + t_big_full);
+
+        // Prove delta * U = G and delta * V + r_x * H = C_x + T' for delta = 1 / d
+        let delta = d.invert();
+        let k_delta = nonces.scalar();
+        let k_rho = nonces.scalar();
+        let c_big_u = k_delta * u_big;
+        let c_big_v = (k_delta * v_big) + (&k_rho * &PUBLIC_PARAMS.h_big);
+
+        let e = response_challenge(cpk, &t_big_full, &u_big, &v_big, &c_big_u, &c_big_v);
+        let a_delta = k_delta + (e * delta);
+        let a_rho = k_rho + (e * csk.r_x);
+
+        Ok(CredentialResponse {
+            u_big,
+            v_big,
+            pi: IssuanceProof { e, a_delta, a_rho },
+        })
+    }
+}
+
+impl Credential {
+    pub fn create(
+        cpk: &CredentialPublicKey,
+        request: &CredentialRequest,
+        receipt: &CredentialReceipt,
+        response: &CredentialResponse,
+    ) -> Result<Credential, ()> {
+        if response.u_big == RistrettoPoint::identity() {
+            return Err(());
+        }
+
+        // C_u <-- a_delta * U - e * G
+        // C_v <-- a_delta * V + a_rho * H - e * (C_x + T')
+        let t_big_full = full_ticket(cpk, request);
+        let c_big_u =
+            (response.pi.a_delta * response.u_big) - (&response.pi.e * &PUBLIC_PARAMS.g_big);
+        let c_big_v = (response.pi.a_delta * response.v_big)
+            + (&response.pi.a_rho * &PUBLIC_PARAMS.h_big)
+            - (response.pi.e * (cpk.c_big_x + t_big_full));
+
+        let e_verify = response_challenge(
+            cpk,
+            &t_big_full,
+            &response.u_big,
+            &response.v_big,
+            &c_big_u,
+            &c_big_v,
+        );
+        if e_verify != response.pi.e {
+            return Err(());
+        }
+
+        // W = V - r * U = (x + sum m_i * z_i) * U
+        Ok(Credential {
+            u_big: response.u_big,
+            w_big: response.v_big - (receipt.r * response.u_big),
+            attributes: receipt.attributes.clone(),
+        })
+    }
+}
+
+impl CredentialPresentation {
+    pub fn create<R>(
+        rng: &mut R,
+        cpk: &CredentialPublicKey,
+        credential: &Credential,
+        reveal: &[usize],
+        predicates: &[Predicate],
+        context: &[u8],
+    ) -> Result<CredentialPresentation, ()>
+    where
+        R: RngCore + CryptoRng,
+    {
+        let n = credential.attributes.len();
+        if cpk.z_big.len() != n || reveal.iter().any(|index| *index >= n) {
+            return Err(());
+        }
+        for Predicate::OneOf { index, values } in predicates {
+            let hidden = *index < n && !reveal.contains(index);
+            if !hidden || !values.contains(&credential.attributes[*index]) {
+                return Err(());
+            }
+        }
+
+        // U' = a * U, W' = a * W; C_w = W' + r_w * G
+        let a = non_zero_scalar(rng);
+        let u_big = a * credential.u_big;
+        let r_w = Scalar::random(rng);
+        let c_big_w = (a * credential.w_big) + (&r_w * &PUBLIC_PARAMS.g_big);
+
+        let mut revealed = Vec::with_capacity(n);
+        let mut c_big = Vec::with_capacity(n);
+        let mut r = Vec::with_capacity(n);
+        for (index, m) in credential.attributes.iter().enumerate() {
+            if reveal.contains(&index) {
+                revealed.push(Some(*m));
+                c_big.push(None);
+                r.push(None);
+            } else {
+                // C_i = m_i * U' + r_i * G
+                let r_i = Scalar::random(rng);
+                revealed.push(None);
+                c_big.push(Some((m * u_big) + (&r_i * &PUBLIC_PARAMS.g_big)));
+                r.push(Some(r_i));
+            }
+        }
+
+        // Commitments for the representation proof
+        let k_w = Scalar::random(rng);
+        let k_m: Vec<Option<Scalar>> = r
+            .iter()
+            .map(|r_i| r_i.map(|_| Scalar::random(rng)))
+            .collect();
+        let k_r: Vec<Option<Scalar>> = r
+            .iter()
+            .map(|r_i| r_i.map(|_| Scalar::random(rng)))
+            .collect();
+
+        let mut c_big_m = Vec::with_capacity(n);
+        let mut c_big_v = -(&k_w * &PUBLIC_PARAMS.g_big);
+        for index in 0..n {
+            match (k_m[index], k_r[index]) {
+                (Some(k_m), Some(k_r)) => {
+                    c_big_m.push(Some((k_m * u_big) + (&k_r * &PUBLIC_PARAMS.g_big)));
+                    c_big_v += k_r * cpk.z_big[index];
+                }
+                _ => c_big_m.push(None),
+            }
+        }
+
+        // Set membership: an OR proof that C_i - v * U' = r_i * G for some v
+        let mut set_commitments = Vec::with_capacity(predicates.len());
+        let mut set_simulated = Vec::with_capacity(predicates.len());
+        for Predicate::OneOf { index, values } in predicates {
+            let c_big_i = c_big[*index].expect("predicate on a hidden attribute");
+            let m = credential.attributes[*index];
+            let k = Scalar::random(rng);
+            let mut commitments = Vec::with_capacity(values.len());
+            let mut simulated = Vec::with_capacity(values.len());
+            for v in values {
+                if *v == m
+                    && simulated
+                        .iter()
+                        .all(|s: &Option<(Scalar, Scalar)>| s.is_some())
+                {
+                    commitments.push(&k * &PUBLIC_PARAMS.g_big);
+                    simulated.push(None);
+                } else {
+                    let e_j = Scalar::random(rng);
+                    let a_j = Scalar::random(rng);
+                    commitments
+                        .push((&a_j * &PUBLIC_PARAMS.g_big) - (e_j * (c_big_i - (v * u_big))));
+                    simulated.push(Some((e_j, a_j)));
+                }
+            }
+            set_commitments.push(commitments);
+            set_simulated.push((k, simulated));
+        }
+
+        let e = presentation_challenge(
+            cpk,
+            &u_big,
+            &c_big_w,
+            &revealed,
+            &c_big,
+            predicates,
+            &c_big_m,
+            &c_big_v,
+            &set_commitments,
+            context,
+        );
+
+        let a_w = k_w + (e * r_w);
+        let a_m = (0..n)
+            .map(|index| k_m[index].map(|k| k + (e * credential.attributes[index])))
+            .collect();
+        let a_r = (0..n)
+            .map(|index| match (k_r[index], r[index]) {
+                (Some(k), Some(r_i)) => Some(k + (e * r_i)),
+                _ => None,
+            })
+            .collect();
+
+        let set_proofs = predicates
+            .iter()
+            .zip(set_simulated)
+            .map(|(Predicate::OneOf { index, .. }, (k, simulated))| {
+                let r_i = r[*index].expect("predicate on a hidden attribute");
+                let e_simulated: Scalar = simulated.iter().flatten().map(|(e_j, _)| e_j).sum();
+                let e_real = e - e_simulated;
+
+                let (e, a) = simulated
+                    .iter()
+                    .map(|s| match s {
+                        Some((e_j, a_j)) => (*e_j, *a_j),
+                        None => (e_real, k + (e_real * r_i)),
+                    })
+                    .unzip();
+                SetMembershipProof { e, a }
+            })
+            .collect();
+
+        Ok(CredentialPresentation {
+            u_big,
+            c_big_w,
+            revealed,
+            c_big,
+            predicates: predicates.to_vec(),
+            e,
+            a_w,
+            a_m,
+            a_r,
+            set_proofs,
+        })
+    }
+
+    pub fn verify(
+        &self,
+        csk: &CredentialSecretKey,
+        cpk: &CredentialPublicKey,
+        context: &[u8],
+    ) -> Result<(), ()> {
+        let n = csk.z.len();
+        if self.u_big == RistrettoPoint::identity()
+            || cpk.z_big.len() != n
+            || self.revealed.len() != n
+            || self.c_big.len() != n
+            || self.a_m.len() != n
+            || self.a_r.len() != n
+            || self.set_proofs.len() != self.predicates.len()
+        {
+            return Err(());
+        }
+
+        // V' = x * U' + sum_revealed m_j * z_j * U' + sum_hidden z_i * C_i - C_w
+        let mut exponent = csk.x;
+        let mut v_big = -self.c_big_w;
+        for index in 0..n {
+            match (self.revealed[index], self.c_big[index]) {
+                (Some(m), None) => exponent += m * csk.z[index],
+                (None, Some(c_big_i)) => v_big += csk.z[index] * c_big_i,
+                _ => return Err(()),
+            }
+        }
+        v_big += exponent * self.u_big;
+
+        // C_m_i <-- a_m_i * U' + a_r_i * G - e * C_i
+        // C_v <-- sum_hidden a_r_i * Z_i - a_w * G - e * V'
+        let mut c_big_m = Vec::with_capacity(n);
+        let mut c_big_v = -(&self.a_w * &PUBLIC_PARAMS.g_big) - (self.e * v_big);
+        for index in 0..n {
+            match (self.c_big[index], self.a_m[index], self.a_r[index]) {
+                (Some(c_big_i), Some(a_m), Some(a_r)) => {
+                    c_big_m.push(Some(
+                        (a_m * self.u_big) + (&a_r * &PUBLIC_PARAMS.g_big) - (self.e * c_big_i),
+                    ));
+                    c_big_v += a_r * cpk.z_big[index];
+                }
+                (None, None, None) => c_big_m.push(None),
+                _ => return Err(()),
+            }
+        }
+
+        let mut set_commitments = Vec::with_capacity(self.predicates.len());
+        for (Predicate::OneOf { index, values }, proof) in
+            self.predicates.iter().zip(&self.set_proofs)
+        {
+            let c_big_i = self.c_big.get(*index).copied().flatten().ok_or(())?;
+            if values.is_empty() || proof.e.len() != values.len() || proof.a.len() != values.len() {
+                return Err(());
+            }
+            let e_sum: Scalar = proof.e.iter().sum();
+            if e_sum != self.e {
+                return Err(());
+            }
+            set_commitments.push(
+                values
+                    .iter()
+                    .zip(proof.e.iter().zip(&proof.a))
+                    .map(|(v, (e_j, a_j))| {
+                        (a_j * &PUBLIC_PARAMS.g_big) - (e_j * (c_big_i - (v * self.u_big)))
+                    })
+                    .collect(),
+            );
+        }
+
+        let e_verify = presentation_challenge(
+            cpk,
+            &self.u_big,
+            &self.c_big_w,
+            &self.revealed,
+            &self.c_big,
+            &self.predicates,
+            &c_big_m,
+            &c_big_v,
+            &set_commitments,
+            context,
+        );
+        if e_verify != self.e {
+            return Err(());
+        }
+
+        Ok(())
+    }
+}
+
+// T' = T + sum_revealed m_j * Z_j
+fn full_ticket(cpk: &CredentialPublicKey, request: &CredentialRequest) -> RistrettoPoint {
+    let mut t_big = request.t_big;
+    for (revealed, z_big) in request.revealed.iter().zip(&cpk.z_big) {
+        if let Some(m) = revealed {
+            t_big += m * z_big;
+        }
+    }
+    t_big
+}
+
+fn hash_public_key(hasher: &mut Sha512, cpk: &CredentialPublicKey) {
+    hasher.update(ristretto_bytes(&cpk.c_big_x));
+    for z_big in &cpk.z_big {
+        hasher.update(ristretto_bytes(z_big));
+    }
+}
+
+fn hash_optional_scalars(hasher: &mut Sha512, scalars: &[Option<Scalar>]) {
+    for scalar in scalars {
+        match scalar {
+            Some(scalar) => {
+                hasher.update([1u8]);
+                hasher.update(scalar_bytes(scalar));
+            }
+            None => hasher.update([0u8]),
+        }
+    }
+}
+
+fn hash_optional_points(hasher: &mut Sha512, points: &[Option<RistrettoPoint>]) {
+    for point in points {
+        match point {
+            Some(point) => {
+                hasher.update([1u8]);
+                hasher.update(ristretto_bytes(point));
+            }
+            None => hasher.update([0u8]),
+        }
+    }
+}
+
+fn request_challenge(
+    cpk: &CredentialPublicKey,
+    t_big: &RistrettoPoint,
+    revealed: &[Option<Scalar>],
+    c_big_t: &RistrettoPoint,
+) -> Scalar {
+    let mut hasher = Sha512::new();
+    hasher.update(b"MacTok-CredentialRequest");
+    hash_public_key(&mut hasher, cpk);
+    hasher.update(ristretto_bytes(t_big));
+    hash_optional_scalars(&mut hasher, revealed);
+    hasher.update(ristretto_bytes(c_big_t));
+
+    Scalar::from_hash(hasher)
+}
+
+fn response_challenge(
+    cpk: &CredentialPublicKey,
+    t_big: &RistrettoPoint,
+    u_big: &RistrettoPoint,
+    v_big: &RistrettoPoint,
+    c_big_u: &RistrettoPoint,
+    c_big_v: &RistrettoPoint,
+) -> Scalar {
+    let mut hasher = Sha512::new();
+    hasher.update(b"MacTok-CredentialResponse");
+    hash_public_key(&mut hasher, cpk);
+    hasher.update(ristretto_bytes(t_big));
+    hasher.update(ristretto_bytes(u_big));
+    hasher.update(ristretto_bytes(v_big));
+    hasher.update(ristretto_bytes(c_big_u));
+    hasher.update(ristretto_bytes(c_big_v));
+
+    Scalar::from_hash(hasher)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn presentation_challenge(
+    cpk: &CredentialPublicKey,
+    u_big: &RistrettoPoint,
+    c_big_w: &RistrettoPoint,
+    revealed: &[Option<Scalar>],
+    c_big: &[Option<RistrettoPoint>],
+    predicates: &[Predicate],
+    c_big_m: &[Option<RistrettoPoint>],
+    c_big_v: &RistrettoPoint,
+    set_commitments: &[Vec<RistrettoPoint>],
+    context: &[u8],
+) -> Scalar {
+    let mut hasher = Sha512::new();
+    hasher.update(b"MacTok-CredentialPresentation");
+    hash_public_key(&mut hasher, cpk);
+    hasher.update(ristretto_bytes(u_big));
+    hasher.update(ristretto_bytes(c_big_w));
+    hash_optional_scalars(&mut hasher, revealed);
+    hash_optional_points(&mut hasher, c_big);
+    for Predicate::OneOf { index, values } in predicates {
+        hasher.update((*index as u64).to_be_bytes());
+        hasher.update((values.len() as u64).to_be_bytes());
+        for v in values {
+            hasher.update(scalar_bytes(v));
+        }
+    }
+    hash_optional_points(&mut hasher, c_big_m);
+    hasher.update(ristretto_bytes(c_big_v));
+    for commitments in set_commitments {
+        for commitment in commitments {
+            hasher.update(ristretto_bytes(commitment));
+        }
+    }
+    hasher.update((context.len() as u64).to_be_bytes());
+    hasher.update(context);
+
+    Scalar::from_hash(hasher)
+}
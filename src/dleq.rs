@@ -0,0 +1,160 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use curve25519_dalek_ng::{ristretto::RistrettoPoint, scalar::Scalar};
+use rand_core::{CryptoRng, RngCore};
+use sha2::{Digest, Sha512};
+
+use crate::utils::{non_zero_scalar, ristretto_bytes};
+
+// Proof that log_{B_1} P_1 = log_{B_2} P_2, bound to a context string
+#[derive(Debug, Clone, PartialEq)]
+pub struct DleqProof {
+    pub e: Scalar,
+    pub a: Scalar,
+}
+
+// Proof of knowledge of log_B P, bound to a context string
+#[derive(Debug, Clone, PartialEq)]
+pub struct KnowledgeProof {
+    pub e: Scalar,
+    pub a: Scalar,
+}
+
+impl DleqProof {
+    pub fn create<R>(
+        rng: &mut R,
+        k: &Scalar,
+        b_big_1: &RistrettoPoint,
+        p_big_1: &RistrettoPoint,
+        b_big_2: &RistrettoPoint,
+        p_big_2: &RistrettoPoint,
+        context: &[u8],
+    ) -> DleqProof
+    where
+        R: RngCore + CryptoRng,
+    {
+        // r <-- ZZ_p*; C_1 <-- r * B_1; C_2 <-- r * B_2
+        let r = non_zero_scalar(rng);
+        let c_big_1 = r * b_big_1;
+        let c_big_2 = r * b_big_2;
+
+        // e <-- Hash(B_1, P_1, B_2, P_2, C_1, C_2, context); a <-- r + e * k
+        let e = challenge(
+            b_big_1, p_big_1, b_big_2, p_big_2, &c_big_1, &c_big_2, context,
+        );
+        let a = r + (e * k);
+
+        DleqProof { e, a }
+    }
+
+    pub fn verify(
+        &self,
+        b_big_1: &RistrettoPoint,
+        p_big_1: &RistrettoPoint,
+        b_big_2: &RistrettoPoint,
+        p_big_2: &RistrettoPoint,
+        context: &[u8],
+    ) -> Result<(), ()> {
+        // C_i <-- a * B_i - e * P_i
+        let c_big_1 = (self.a * b_big_1) - (self.e * p_big_1);
+        let c_big_2 = (self.a * b_big_2) - (self.e * p_big_2);
+
+        let e_verify = challenge(
+            b_big_1, p_big_1, b_big_2, p_big_2, &c_big_1, &c_big_2, context,
+        );
+        if e_verify != self.e {
+            return Err(());
+        }
+
+        Ok(())
+    }
+}
+
+impl KnowledgeProof {
+    pub fn create<R>(
+        rng: &mut R,
+        k: &Scalar,
+        b_big: &RistrettoPoint,
+        p_big: &RistrettoPoint,
+        context: &[u8],
+    ) -> KnowledgeProof
+    where
+        R: RngCore + CryptoRng,
+    {
+        // r <-- ZZ_p*
+        KnowledgeProof::create_with_nonce(&non_zero_scalar(rng), k, b_big, p_big, context)
+    }
+
+    // For callers that derive r themselves, e.g. from HedgedNonces
+    pub(crate) fn create_with_nonce(
+        r: &Scalar,
+        k: &Scalar,
+        b_big: &RistrettoPoint,
+        p_big: &RistrettoPoint,
+        context: &[u8],
+    ) -> KnowledgeProof {
+        // C <-- r * B; e <-- Hash(B, P, C, context); a <-- r + e * k
+        let c_big = r * b_big;
+        let e = knowledge_challenge(b_big, p_big, &c_big, context);
+        let a = r + (e * k);
+
+        KnowledgeProof { e, a }
+    }
+
+    pub fn verify(
+        &self,
+        b_big: &RistrettoPoint,
+        p_big: &RistrettoPoint,
+        context: &[u8],
+    ) -> Result<(), ()> {
+        // C <-- a * B - e * P
+        let c_big = (self.a * b_big) - (self.e * p_big);
+
+        if knowledge_challenge(b_big, p_big, &c_big, context) != self.e {
+            return Err(());
+        }
+
+        Ok(())
+    }
+}
+
+fn challenge(
+    b_big_1: &RistrettoPoint,
+    p_big_1: &RistrettoPoint,
+    b_big_2: &RistrettoPoint,
+    p_big_2: &RistrettoPoint,
+    c_big_1: &RistrettoPoint,
+    c_big_2: &RistrettoPoint,
+    context: &[u8],
+) -> Scalar {
+    let mut hasher = Sha512::new();
+    hasher.update(b"MacTok-DLEQ");
+    hasher.update(ristretto_bytes(b_big_1));
+    hasher.update(ristretto_bytes(p_big_1));
+    hasher.update(ristretto_bytes(b_big_2));
+    hasher.update(ristretto_bytes(p_big_2));
+    hasher.update(ristretto_bytes(c_big_1));
+    hasher.update(ristretto_bytes(c_big_2));
+    hasher.update((context.len() as u64).to_be_bytes());
+    hasher.update(context);
+
+    Scalar::from_hash(hasher)
+}
+
+fn knowledge_challenge(
+    b_big: &RistrettoPoint,
+    p_big: &RistrettoPoint,
+    c_big: &RistrettoPoint,
+    context: &[u8],
+) -> Scalar {
+    let mut hasher = Sha512::new();
+    hasher.update(b"MacTok-PoK");
+    hasher.update(ristretto_bytes(b_big));
+    hasher.update(ristretto_bytes(p_big));
+    hasher.update(ristretto_bytes(c_big));
+    hasher.update((context.len() as u64).to_be_bytes());
+    hasher.update(context);
+
+    Scalar::from_hash(hasher)
+}
@@ -0,0 +1,129 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use std::collections::HashMap;
+
+use curve25519_dalek_ng::scalar::Scalar;
+use rand_core::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    blind_sig::BlindSignature,
+    keys::{PublicKey, SecretKey},
+    spent_tokens::SpentTokens,
+    ticket::{Receipt, Ticket},
+    token::Token,
+};
+
+// Times are seconds since the UNIX epoch; an epoch covers [not_before, not_after)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Epoch {
+    pub id: u32,
+    pub not_before: u64,
+    pub not_after: u64,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct EpochSecretKey {
+    pub epoch: Epoch,
+    pub sk: SecretKey,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct EpochPublicKey {
+    pub epoch: Epoch,
+    pub pk: PublicKey,
+}
+
+pub struct EpochToken {
+    pub epoch: u32,
+    pub token: Token,
+}
+
+// Spent tokens grouped by epoch, so that expired epochs can be dropped as a whole
+#[derive(Debug, Default)]
+pub struct EpochSpentTokens {
+    epochs: HashMap<u32, (Epoch, SpentTokens)>,
+}
+
+impl Epoch {
+    pub fn is_valid_at(&self, now: u64) -> bool {
+        self.not_before <= now && now < self.not_after
+    }
+
+    pub fn is_expired_at(&self, now: u64) -> bool {
+        now >= self.not_after
+    }
+}
+
+impl EpochSecretKey {
+    pub fn create<R>(rng: &mut R, epoch: Epoch) -> EpochSecretKey
+    where
+        R: RngCore + CryptoRng,
+    {
+        EpochSecretKey {
+            epoch,
+            sk: SecretKey::create(rng),
+        }
+    }
+}
+
+impl EpochPublicKey {
+    pub fn create(secret_key: &EpochSecretKey) -> EpochPublicKey {
+        EpochPublicKey {
+            epoch: secret_key.epoch,
+            pk: PublicKey::create(&secret_key.sk),
+        }
+    }
+}
+
+impl EpochToken {
+    pub fn create<R>(
+        rng: &mut R,
+        epk: &EpochPublicKey,
+        bs: &BlindSignature,
+        ticket: &Ticket,
+        receipt: &Receipt,
+    ) -> Result<EpochToken, ()>
+    where
+        R: RngCore + CryptoRng,
+    {
+        let token = Token::create(rng, &epk.pk, bs, ticket, receipt)?;
+
+        Ok(EpochToken {
+            epoch: epk.epoch.id,
+            token,
+        })
+    }
+}
+
+impl EpochSpentTokens {
+    pub fn new() -> EpochSpentTokens {
+        EpochSpentTokens::default()
+    }
+
+    pub fn is_spent(&self, epoch: &Epoch, t: &Scalar) -> bool {
+        match self.epochs.get(&epoch.id) {
+            Some((_, spent)) => spent.is_spent(t),
+            None => false,
+        }
+    }
+
+    // Returns false if the token was already spent in this epoch
+    pub fn mark_spent(&mut self, epoch: &Epoch, t: &Scalar) -> bool {
+        let (_, spent) = self
+            .epochs
+            .entry(epoch.id)
+            .or_insert_with(|| (*epoch, SpentTokens::new()));
+        spent.mark_spent(t)
+    }
+
+    // Tokens from expired epochs are rejected before the spent check, so their state can go
+    pub fn prune(&mut self, now: u64) {
+        self.epochs.retain(|_, (epoch, _)| !epoch.is_expired_at(now));
+    }
+
+    pub fn epoch_count(&self) -> usize {
+        self.epochs.len()
+    }
+}
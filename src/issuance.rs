@@ -0,0 +1,206 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+// Issuance messages. The client sends a batch of tickets for one key under a
+// fresh nonce, optionally with a proof for the issuer's policy (an attestation,
+// a proof of work, ...), and the issuer answers with one signature per ticket
+// under the same nonce.
+//
+//   request   uint8 version, key_id, nonce, uint16 count, count * T,
+//             uint8 has_proof, [uint16 proof_len, proof]
+//   response  uint8 version, key_id, nonce, uint16 count,
+//             count * BlindSignature
+
+use rand_core::{CryptoRng, RngCore};
+
+use crate::{
+    blind_sig::BlindSignature,
+    client::{PendingIssuance, UnspentToken},
+    keys::{KeyId, PublicKey},
+    ticket::Ticket,
+    utils::ByteReader,
+};
+
+pub const ISSUANCE_VERSION: u8 = 1;
+
+#[derive(Debug, PartialEq)]
+pub struct IssuanceRequest {
+    pub version: u8,
+    pub key_id: KeyId,
+    pub nonce: [u8; 32],
+    pub tickets: Vec<Ticket>,
+    pub proof: Option<Vec<u8>>,
+}
+
+pub struct IssuanceResponse {
+    pub version: u8,
+    pub key_id: KeyId,
+    pub nonce: [u8; 32],
+    pub signatures: Vec<BlindSignature>,
+}
+
+// Client state between sending a request and receiving its response
+pub struct PendingBatch {
+    key_id: KeyId,
+    nonce: [u8; 32],
+    pending: Vec<PendingIssuance>,
+}
+
+impl IssuanceRequest {
+    pub fn create<R>(
+        rng: &mut R,
+        pk: &PublicKey,
+        count: u16,
+        proof: Option<Vec<u8>>,
+    ) -> Result<(IssuanceRequest, PendingBatch), ()>
+    where
+        R: RngCore + CryptoRng,
+    {
+        if proof.as_ref().is_some_and(|p| p.len() > u16::MAX as usize) {
+            return Err(());
+        }
+
+        let mut nonce = [0u8; 32];
+        rng.fill_bytes(&mut nonce);
+        let pending: Vec<PendingIssuance> = (0..count)
+            .map(|_| PendingIssuance::create(rng, pk))
+            .collect();
+
+        let request = IssuanceRequest {
+            version: ISSUANCE_VERSION,
+            key_id: pk.key_id(),
+            nonce,
+            tickets: pending
+                .iter()
+                .map(|p| Ticket {
+                    t_big: p.ticket().t_big,
+                })
+                .collect(),
+            proof,
+        };
+        let batch = PendingBatch {
+            key_id: request.key_id,
+            nonce,
+            pending,
+        };
+
+        Ok((request, batch))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + 32 + 32 + 2 + 32 * self.tickets.len() + 1);
+        bytes.push(self.version);
+        bytes.extend_from_slice(&self.key_id);
+        bytes.extend_from_slice(&self.nonce);
+        bytes.extend_from_slice(&(self.tickets.len() as u16).to_be_bytes());
+        for ticket in &self.tickets {
+            bytes.extend_from_slice(&ticket.to_bytes());
+        }
+        match &self.proof {
+            Some(proof) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&(proof.len() as u16).to_be_bytes());
+                bytes.extend_from_slice(proof);
+            }
+            None => bytes.push(0),
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<IssuanceRequest, ()> {
+        let mut reader = ByteReader::new(bytes);
+        let version = reader.read_array::<1>()?[0];
+        if version != ISSUANCE_VERSION {
+            return Err(());
+        }
+        let key_id = reader.read_array()?;
+        let nonce = reader.read_array()?;
+        let count = reader.read_u16()?;
+        let tickets = (0..count)
+            .map(|_| Ticket::from_bytes(reader.read(32)?))
+            .collect::<Result<_, ()>>()?;
+        let proof = match reader.read_array::<1>()?[0] {
+            0 => None,
+            1 => {
+                let len = reader.read_u16()?;
+                Some(reader.read(len as usize)?.to_vec())
+            }
+            _ => return Err(()),
+        };
+        reader.finish()?;
+
+        Ok(IssuanceRequest {
+            version,
+            key_id,
+            nonce,
+            tickets,
+            proof,
+        })
+    }
+}
+
+impl IssuanceResponse {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes =
+            Vec::with_capacity(1 + 32 + 32 + 2 + BlindSignature::SIZE * self.signatures.len());
+        bytes.push(self.version);
+        bytes.extend_from_slice(&self.key_id);
+        bytes.extend_from_slice(&self.nonce);
+        bytes.extend_from_slice(&(self.signatures.len() as u16).to_be_bytes());
+        for signature in &self.signatures {
+            bytes.extend_from_slice(&signature.to_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<IssuanceResponse, ()> {
+        let mut reader = ByteReader::new(bytes);
+        let version = reader.read_array::<1>()?[0];
+        if version != ISSUANCE_VERSION {
+            return Err(());
+        }
+        let key_id = reader.read_array()?;
+        let nonce = reader.read_array()?;
+        let count = reader.read_u16()?;
+        let signatures = (0..count)
+            .map(|_| BlindSignature::from_bytes(reader.read(BlindSignature::SIZE)?))
+            .collect::<Result<_, ()>>()?;
+        reader.finish()?;
+
+        Ok(IssuanceResponse {
+            version,
+            key_id,
+            nonce,
+            signatures,
+        })
+    }
+}
+
+impl PendingBatch {
+    // Client handler: checks the response answers this request and finalizes
+    // every ticket. Fails as a whole if any signature does not verify.
+    pub fn finalize<R>(
+        self,
+        rng: &mut R,
+        pk: &PublicKey,
+        response: &[u8],
+    ) -> Result<Vec<UnspentToken>, ()>
+    where
+        R: RngCore + CryptoRng,
+    {
+        let response = IssuanceResponse::from_bytes(response)?;
+        if response.key_id != self.key_id
+            || response.key_id != pk.key_id()
+            || response.nonce != self.nonce
+            || response.signatures.len() != self.pending.len()
+        {
+            return Err(());
+        }
+
+        self.pending
+            .into_iter()
+            .zip(&response.signatures)
+            .map(|(pending, signature)| pending.finalize(rng, pk, signature))
+            .collect()
+    }
+}
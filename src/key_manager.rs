@@ -0,0 +1,133 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use rand_core::{CryptoRng, RngCore};
+
+use crate::{
+    epoch::{Epoch, EpochPublicKey, EpochSecretKey},
+    keys::{KeyId, PublicKey},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyState {
+    // Published so clients can fetch it ahead of time, not yet used
+    Pending,
+    // Used for issuance and redemption
+    Active,
+    // No longer issues, still redeems tokens clients already hold
+    RedeemOnly,
+    Retired,
+}
+
+pub struct ManagedKey {
+    pub key: EpochSecretKey,
+    pub key_id: KeyId,
+    // Issuance stops at issue_until, redemption at key.epoch.not_after
+    pub issue_until: u64,
+}
+
+// Rotates epoch keys every rotation_period seconds; each key stays redeemable
+// for grace_period seconds after its successor takes over issuance
+pub struct KeyManager {
+    rotation_period: u64,
+    grace_period: u64,
+    next_epoch_id: u32,
+    keys: Vec<ManagedKey>,
+}
+
+impl ManagedKey {
+    pub fn state(&self, now: u64) -> KeyState {
+        if now < self.key.epoch.not_before {
+            KeyState::Pending
+        } else if now < self.issue_until {
+            KeyState::Active
+        } else if now < self.key.epoch.not_after {
+            KeyState::RedeemOnly
+        } else {
+            KeyState::Retired
+        }
+    }
+}
+
+impl KeyManager {
+    pub fn new(rotation_period: u64, grace_period: u64) -> KeyManager {
+        assert!(rotation_period > 0, "rotation period must be positive");
+
+        KeyManager {
+            rotation_period,
+            grace_period,
+            next_epoch_id: 0,
+            keys: Vec::new(),
+        }
+    }
+
+    // Drops retired keys and makes sure there is an active key for now and a
+    // pending one for the next period
+    pub fn rotate<R>(&mut self, rng: &mut R, now: u64)
+    where
+        R: RngCore + CryptoRng,
+    {
+        self.keys
+            .retain(|managed| managed.state(now) != KeyState::Retired);
+
+        let horizon = now.saturating_add(self.rotation_period);
+        loop {
+            let not_before = match self.keys.last() {
+                Some(last) if last.issue_until > horizon => break,
+                // Keep the schedule contiguous, but never start in the past
+                Some(last) => last.issue_until.max(now),
+                None => now,
+            };
+            let issue_until = not_before.saturating_add(self.rotation_period);
+            let epoch = Epoch {
+                id: self.next_epoch_id,
+                not_before,
+                not_after: issue_until.saturating_add(self.grace_period),
+            };
+            self.next_epoch_id = self.next_epoch_id.wrapping_add(1);
+
+            let key = EpochSecretKey::create(rng, epoch);
+            self.keys.push(ManagedKey {
+                key_id: PublicKey::create(&key.sk).key_id(),
+                key,
+                issue_until,
+            });
+        }
+    }
+
+    pub fn state(&self, epoch_id: u32, now: u64) -> Option<KeyState> {
+        self.find(epoch_id).map(|managed| managed.state(now))
+    }
+
+    pub fn issuing_key(&self, now: u64) -> Option<&EpochSecretKey> {
+        self.keys
+            .iter()
+            .find(|managed| managed.state(now) == KeyState::Active)
+            .map(|managed| &managed.key)
+    }
+
+    pub fn redemption_key(&self, epoch_id: u32, now: u64) -> Option<&EpochSecretKey> {
+        self.find(epoch_id)
+            .filter(|managed| matches!(managed.state(now), KeyState::Active | KeyState::RedeemOnly))
+            .map(|managed| &managed.key)
+    }
+
+    // The key set clients should accept: pending, active and redeem-only keys
+    pub fn public_keys(&self, now: u64) -> Vec<EpochPublicKey> {
+        self.keys
+            .iter()
+            .filter(|managed| managed.state(now) != KeyState::Retired)
+            .map(|managed| EpochPublicKey::create(&managed.key))
+            .collect()
+    }
+
+    pub fn keys(&self) -> &[ManagedKey] {
+        &self.keys
+    }
+
+    fn find(&self, epoch_id: u32) -> Option<&ManagedKey> {
+        self.keys
+            .iter()
+            .find(|managed| managed.key.epoch.id == epoch_id)
+    }
+}
@@ -5,8 +5,11 @@ use crate::params::PUBLIC_PARAMS;
 use curve25519_dalek_ng::{ristretto::RistrettoPoint, scalar::Scalar};
 use rand_core::{CryptoRng, RngCore};
 use serde::{Deserialize, Serialize}; // G
+use sha2::{Digest, Sha256};
 
-use crate::utils::non_zero_scalar;
+use crate::utils::{non_zero_scalar, ristretto_bytes};
+
+pub type KeyId = [u8; 32];
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct PublicKey {
@@ -65,4 +68,14 @@ impl PublicKey {
         };
         spk
     }
+
+    // SHA-256 over the public key points
+    pub fn key_id(&self) -> KeyId {
+        let mut hasher = Sha256::new();
+        hasher.update(ristretto_bytes(&self.z_big));
+        hasher.update(ristretto_bytes(&self.c_big_x));
+        hasher.update(ristretto_bytes(&self.c_big_y));
+
+        hasher.finalize().into()
+    }
 }
@@ -1,15 +1,59 @@
 // Copyright (c) Microsoft Corporation. All rights reserved.
 // Licensed under the MIT license.
 
+// Failures are reported as Result<_, ()>: callers learn that an operation
+// failed, never why, so nothing about keys or tokens leaks through the error.
+// Modules with such functions allow clippy::result_unit_err.
+
+#[allow(clippy::result_unit_err)]
 pub mod blind_sig;
+pub mod bound_token;
+pub mod challenge;
+#[allow(clippy::result_unit_err)]
+pub mod client;
+#[allow(clippy::result_unit_err)]
+pub mod client_binding;
+#[allow(clippy::result_unit_err)]
+pub mod credential;
+#[allow(clippy::result_unit_err)]
+pub mod dleq;
+#[allow(clippy::result_unit_err)]
+pub mod epoch;
+#[allow(clippy::result_unit_err)]
+pub mod issuance;
+pub mod key_manager;
 pub mod keys;
+mod nonce;
 pub mod params;
+#[allow(clippy::result_unit_err)]
+pub mod policy;
+pub mod presentation;
+#[allow(clippy::result_unit_err)]
+pub mod privacy_pass;
+#[allow(clippy::result_unit_err)]
 pub mod prover_server;
+pub mod rate_limit;
+#[allow(clippy::result_unit_err)]
+pub mod scheme;
+#[allow(clippy::result_unit_err)]
 pub mod server;
+#[cfg(feature = "async")]
+pub mod service;
+#[allow(clippy::result_unit_err)]
+pub mod signer;
+pub mod spent_tokens;
+#[allow(clippy::result_unit_err)]
+pub mod split_redemption;
+#[allow(clippy::result_unit_err)]
+pub mod threshold;
+#[allow(clippy::result_unit_err)]
 pub mod ticket;
+#[allow(clippy::result_unit_err)]
 pub mod token;
 mod utils;
 pub mod verifier_client;
+#[allow(clippy::result_unit_err)]
+pub mod voprf;
 
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,83 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use curve25519_dalek_ng::scalar::Scalar;
+use rand_core::{CryptoRng, RngCore};
+use sha2::{Digest, Sha512};
+
+use crate::{keys::SecretKey, utils::scalar_bytes};
+
+// Hedged nonce derivation for the issuer. Every nonce is
+//   H(label || secret key || secrets || transcript || rng output || counter)
+// so a repeated or broken RNG still cannot reuse a nonce across different
+// transcripts, and a good RNG keeps the nonces unpredictable even when the
+// transcript repeats.
+pub(crate) struct HedgedNonces {
+    hasher: Sha512,
+    counter: u64,
+}
+
+impl HedgedNonces {
+    pub fn new<R>(
+        rng: &mut R,
+        label: &[u8],
+        sk: &SecretKey,
+        secrets: &[&Scalar],
+        transcript: &[&[u8]],
+    ) -> HedgedNonces
+    where
+        R: RngCore + CryptoRng,
+    {
+        let secrets: Vec<&Scalar> = [&sk.x, &sk.y, &sk.z, &sk.r_x, &sk.r_y]
+            .into_iter()
+            .chain(secrets.iter().copied())
+            .collect();
+
+        HedgedNonces::with_secrets(rng, label, &secrets, transcript)
+    }
+
+    // For issuers whose key is not a SecretKey; secrets must include the key
+    pub fn with_secrets<R>(
+        rng: &mut R,
+        label: &[u8],
+        secrets: &[&Scalar],
+        transcript: &[&[u8]],
+    ) -> HedgedNonces
+    where
+        R: RngCore + CryptoRng,
+    {
+        let mut hasher = Sha512::new();
+        hasher.update((label.len() as u64).to_be_bytes());
+        hasher.update(label);
+        for scalar in secrets {
+            hasher.update(scalar_bytes(scalar));
+        }
+        hasher.update((transcript.len() as u64).to_be_bytes());
+        for item in transcript {
+            hasher.update((item.len() as u64).to_be_bytes());
+            hasher.update(item);
+        }
+
+        let mut fresh = [0u8; 32];
+        rng.fill_bytes(&mut fresh);
+        hasher.update(fresh);
+
+        HedgedNonces { hasher, counter: 0 }
+    }
+
+    pub fn scalar(&mut self) -> Scalar {
+        let mut hasher = self.hasher.clone();
+        hasher.update(self.counter.to_be_bytes());
+        self.counter += 1;
+        Scalar::from_hash(hasher)
+    }
+
+    pub fn non_zero_scalar(&mut self) -> Scalar {
+        loop {
+            let scalar = self.scalar();
+            if scalar != Scalar::zero() {
+                return scalar;
+            }
+        }
+    }
+}
@@ -0,0 +1,277 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+// Deciding what an issuer signs into a token from what it knows about the
+// request. RulePolicy reads its rules from JSON:
+//
+//   {
+//     "rules": [
+//       { "when": { "attested": false }, "decision": "refuse" },
+//       { "when": { "min_risk": 0.9 }, "decision": "poison" },
+//       { "when": { "header": { "x-client": "crawler" } }, "decision": "false" }
+//     ],
+//     "default": "true"
+//   }
+//
+// Every condition of a rule must hold; the first matching rule decides.
+// Conditions: attested (bool), min_risk and max_risk (min <= score < max),
+// quota_exhausted (bool) and header (exact values, names case-insensitive).
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde_json::Value;
+
+use crate::{blind_sig::PrivateMetadata, issuance::IssuanceRequest};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IssuanceContext {
+    // None if the client presented no attestation
+    pub attestation: Option<bool>,
+    pub risk_score: Option<f64>,
+    // Lowercase names
+    pub headers: BTreeMap<String, String>,
+    pub quota_remaining: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IssuanceDecision {
+    Issue(PrivateMetadata),
+    Refuse,
+}
+
+pub trait IssuancePolicy {
+    fn decide(&self, context: &IssuanceContext) -> IssuanceDecision;
+}
+
+impl<F> IssuancePolicy for F
+where
+    F: Fn(&IssuanceContext) -> IssuanceDecision,
+{
+    fn decide(&self, context: &IssuanceContext) -> IssuanceDecision {
+        self(context)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RuleCondition {
+    pub attested: Option<bool>,
+    pub min_risk: Option<f64>,
+    pub max_risk: Option<f64>,
+    pub quota_exhausted: Option<bool>,
+    pub headers: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub when: RuleCondition,
+    pub decision: IssuanceDecision,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RulePolicy {
+    pub rules: Vec<Rule>,
+    pub default: IssuanceDecision,
+}
+
+// One replayed request and what the policy decided for it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayResult {
+    // 1-based line in the recording
+    pub line: usize,
+    pub decision: IssuanceDecision,
+    pub expected: Option<IssuanceDecision>,
+}
+
+impl IssuanceContext {
+    // The context of an envelope request. verify_proof checks the proof the
+    // client attached, if any; header names may be in any case.
+    pub fn from_request<V>(
+        request: &IssuanceRequest,
+        headers: &[(&str, &str)],
+        verify_proof: V,
+    ) -> IssuanceContext
+    where
+        V: FnOnce(&[u8]) -> bool,
+    {
+        IssuanceContext::from_headers(headers).with_attestation(request, verify_proof)
+    }
+
+    // A context with only headers; the caller adds the risk score and quota
+    pub fn from_headers(headers: &[(&str, &str)]) -> IssuanceContext {
+        IssuanceContext {
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_ascii_lowercase(), value.to_string()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    // Replaces the attestation with the outcome of checking request's proof
+    pub fn with_attestation<V>(self, request: &IssuanceRequest, verify_proof: V) -> IssuanceContext
+    where
+        V: FnOnce(&[u8]) -> bool,
+    {
+        IssuanceContext {
+            attestation: request.proof.as_deref().map(verify_proof),
+            ..self
+        }
+    }
+
+    // { "attestation": bool, "risk_score": number, "headers": { name: value },
+    //   "quota_remaining": number }, every field optional
+    pub fn from_json_value(value: &Value) -> Result<IssuanceContext, ()> {
+        let object = value.as_object().ok_or(())?;
+        let mut context = IssuanceContext::default();
+        for (key, field) in object {
+            match key.as_str() {
+                "attestation" => context.attestation = Some(field.as_bool().ok_or(())?),
+                "risk_score" => context.risk_score = Some(field.as_f64().ok_or(())?),
+                "headers" => context.headers = headers_from_json(field)?,
+                "quota_remaining" => {
+                    let quota = field.as_u64().ok_or(())?;
+                    context.quota_remaining = Some(quota.try_into().map_err(|_| ())?);
+                }
+                _ => return Err(()),
+            }
+        }
+        Ok(context)
+    }
+}
+
+impl RuleCondition {
+    pub fn matches(&self, context: &IssuanceContext) -> bool {
+        if let Some(attested) = self.attested {
+            if context.attestation.unwrap_or(false) != attested {
+                return false;
+            }
+        }
+        // Without a score, only rules that do not look at it match
+        if self.min_risk.is_some() || self.max_risk.is_some() {
+            let score = match context.risk_score {
+                Some(score) => score,
+                None => return false,
+            };
+            if self.min_risk.is_some_and(|min| score < min)
+                || self.max_risk.is_some_and(|max| score >= max)
+            {
+                return false;
+            }
+        }
+        if let Some(exhausted) = self.quota_exhausted {
+            if (context.quota_remaining == Some(0)) != exhausted {
+                return false;
+            }
+        }
+        self.headers
+            .iter()
+            .all(|(name, value)| context.headers.get(name) == Some(value))
+    }
+
+    fn from_json_value(value: &Value) -> Result<RuleCondition, ()> {
+        let object = value.as_object().ok_or(())?;
+        let mut condition = RuleCondition::default();
+        for (key, field) in object {
+            match key.as_str() {
+                "attested" => condition.attested = Some(field.as_bool().ok_or(())?),
+                "min_risk" => condition.min_risk = Some(field.as_f64().ok_or(())?),
+                "max_risk" => condition.max_risk = Some(field.as_f64().ok_or(())?),
+                "quota_exhausted" => condition.quota_exhausted = Some(field.as_bool().ok_or(())?),
+                "header" => condition.headers = headers_from_json(field)?,
+                _ => return Err(()),
+            }
+        }
+        Ok(condition)
+    }
+}
+
+impl RulePolicy {
+    pub fn from_json(config: &str) -> Result<RulePolicy, ()> {
+        let config: Value = serde_json::from_str(config).map_err(|_| ())?;
+        let rules = config["rules"]
+            .as_array()
+            .ok_or(())?
+            .iter()
+            .map(|rule| {
+                Ok(Rule {
+                    when: RuleCondition::from_json_value(&rule["when"])?,
+                    decision: decision_from_json(&rule["decision"])?,
+                })
+            })
+            .collect::<Result<_, ()>>()?;
+
+        Ok(RulePolicy {
+            rules,
+            default: decision_from_json(&config["default"])?,
+        })
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<RulePolicy, ()> {
+        RulePolicy::from_json(&fs::read_to_string(path).map_err(|_| ())?)
+    }
+}
+
+impl IssuancePolicy for RulePolicy {
+    fn decide(&self, context: &IssuanceContext) -> IssuanceDecision {
+        self.rules
+            .iter()
+            .find(|rule| rule.when.matches(context))
+            .map_or(self.default, |rule| rule.decision)
+    }
+}
+
+impl ReplayResult {
+    pub fn matches(&self) -> bool {
+        self.expected
+            .is_none_or(|expected| expected == self.decision)
+    }
+}
+
+// Replays recorded requests, one JSON object per line:
+//   { "context": { ... }, "expected": "true" }
+// expected is optional. Blank lines are skipped.
+pub fn replay<P: IssuancePolicy>(policy: &P, recording: &str) -> Result<Vec<ReplayResult>, ()> {
+    let mut results = Vec::new();
+    for (index, line) in recording.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: Value = serde_json::from_str(line).map_err(|_| ())?;
+        let context = IssuanceContext::from_json_value(&record["context"])?;
+        let expected = match &record["expected"] {
+            Value::Null => None,
+            expected => Some(decision_from_json(expected)?),
+        };
+
+        results.push(ReplayResult {
+            line: index + 1,
+            decision: policy.decide(&context),
+            expected,
+        });
+    }
+    Ok(results)
+}
+
+fn decision_from_json(value: &Value) -> Result<IssuanceDecision, ()> {
+    match value.as_str().ok_or(())? {
+        "true" => Ok(IssuanceDecision::Issue(PrivateMetadata::Bit(true))),
+        "false" => Ok(IssuanceDecision::Issue(PrivateMetadata::Bit(false))),
+        "poison" => Ok(IssuanceDecision::Issue(PrivateMetadata::Poison)),
+        "refuse" => Ok(IssuanceDecision::Refuse),
+        _ => Err(()),
+    }
+}
+
+fn headers_from_json(value: &Value) -> Result<BTreeMap<String, String>, ()> {
+    value
+        .as_object()
+        .ok_or(())?
+        .iter()
+        .map(|(name, value)| {
+            Ok((
+                name.to_ascii_lowercase(),
+                value.as_str().ok_or(())?.to_string(),
+            ))
+        })
+        .collect()
+}
@@ -0,0 +1,133 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use curve25519_dalek_ng::{ristretto::RistrettoPoint, scalar::Scalar, traits::Identity};
+use rand_core::{CryptoRng, RngCore};
+use sha2::{Digest, Sha512};
+
+use crate::{
+    keys::PublicKey,
+    params::PUBLIC_PARAMS,
+    token::Token,
+    utils::{non_zero_scalar, ristretto_bytes},
+};
+
+// A multi-show presentation of a token. The client re-randomizes the MAC to
+// P' = a * P, Q' = a * Q and commits to t and Q':
+//   C_t = t * P' + r_t * G
+//   C_q = Q' + r_q * G
+// With the MAC valid for bit b, the verifier's
+//   (x + b * y) * P' + z * C_t - C_q
+// equals V = r_t * Z - r_q * G, which the client sends along with a proof of
+// knowledge of (t, r_t, r_q). Neither t nor Q ever leaves the client.
+pub struct TokenPresentation {
+    pub p_big: RistrettoPoint,
+    pub c_big_t: RistrettoPoint,
+    pub c_big_q: RistrettoPoint,
+    pub v_big: RistrettoPoint,
+    pub e: Scalar,
+    pub a_t: Scalar,
+    pub a_r_t: Scalar,
+    pub a_r_q: Scalar,
+}
+
+impl TokenPresentation {
+    pub fn create<R>(
+        rng: &mut R,
+        pk: &PublicKey,
+        token: &Token,
+        context: &[u8],
+    ) -> TokenPresentation
+    where
+        R: RngCore + CryptoRng,
+    {
+        let a = non_zero_scalar(rng);
+        let p_big = a * token.p_big;
+        let q_big = a * token.q_big;
+
+        let r_t = Scalar::random(rng);
+        let r_q = Scalar::random(rng);
+        let c_big_t = (token.t * p_big) + (&r_t * &PUBLIC_PARAMS.g_big);
+        let c_big_q = q_big + (&r_q * &PUBLIC_PARAMS.g_big);
+        let v_big = (r_t * pk.z_big) - (&r_q * &PUBLIC_PARAMS.g_big);
+
+        let k_t = Scalar::random(rng);
+        let k_r_t = Scalar::random(rng);
+        let k_r_q = Scalar::random(rng);
+        let c_big_1 = (k_t * p_big) + (&k_r_t * &PUBLIC_PARAMS.g_big);
+        let c_big_2 = (k_r_t * pk.z_big) - (&k_r_q * &PUBLIC_PARAMS.g_big);
+
+        let e = challenge(
+            pk, &p_big, &c_big_t, &c_big_q, &v_big, &c_big_1, &c_big_2, context,
+        );
+
+        TokenPresentation {
+            p_big,
+            c_big_t,
+            c_big_q,
+            v_big,
+            e,
+            a_t: k_t + (e * token.t),
+            a_r_t: k_r_t + (e * r_t),
+            a_r_q: k_r_q + (e * r_q),
+        }
+    }
+
+    // Checks the proof of knowledge; the MAC itself needs the secret key
+    pub(crate) fn verify_proof(&self, pk: &PublicKey, context: &[u8]) -> Result<(), ()> {
+        if self.p_big == RistrettoPoint::identity() {
+            return Err(());
+        }
+
+        // C_1 <-- a_t * P' + a_r_t * G - e * C_t
+        // C_2 <-- a_r_t * Z - a_r_q * G - e * V
+        let c_big_1 = (self.a_t * self.p_big) + (&self.a_r_t * &PUBLIC_PARAMS.g_big)
+            - (self.e * self.c_big_t);
+        let c_big_2 =
+            (self.a_r_t * pk.z_big) - (&self.a_r_q * &PUBLIC_PARAMS.g_big) - (self.e * self.v_big);
+
+        let e_verify = challenge(
+            pk,
+            &self.p_big,
+            &self.c_big_t,
+            &self.c_big_q,
+            &self.v_big,
+            &c_big_1,
+            &c_big_2,
+            context,
+        );
+        if e_verify != self.e {
+            return Err(());
+        }
+
+        Ok(())
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn challenge(
+    pk: &PublicKey,
+    p_big: &RistrettoPoint,
+    c_big_t: &RistrettoPoint,
+    c_big_q: &RistrettoPoint,
+    v_big: &RistrettoPoint,
+    c_big_1: &RistrettoPoint,
+    c_big_2: &RistrettoPoint,
+    context: &[u8],
+) -> Scalar {
+    let mut hasher = Sha512::new();
+    hasher.update(b"MacTok-TokenPresentation");
+    hasher.update(ristretto_bytes(&pk.z_big));
+    hasher.update(ristretto_bytes(&pk.c_big_x));
+    hasher.update(ristretto_bytes(&pk.c_big_y));
+    hasher.update(ristretto_bytes(p_big));
+    hasher.update(ristretto_bytes(c_big_t));
+    hasher.update(ristretto_bytes(c_big_q));
+    hasher.update(ristretto_bytes(v_big));
+    hasher.update(ristretto_bytes(c_big_1));
+    hasher.update(ristretto_bytes(c_big_2));
+    hasher.update((context.len() as u64).to_be_bytes());
+    hasher.update(context);
+
+    Scalar::from_hash(hasher)
+}
@@ -0,0 +1,353 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+// Privacy Pass (RFC 9576, 9577, 9578) framing for this crate's tokens: the
+// TokenChallenge, TokenRequest, TokenResponse and Token structures and the
+// PrivateToken HTTP authentication scheme.
+//
+// The token type is not IANA-registered; issuers and origins must agree on it
+// out of band. The token key is Z || C_x || C_y, so token_key_id is
+// PublicKey::key_id(). The Token nonce is the token's t, and the authenticator
+// is P followed by the BoundToken MAC over the token input
+//   token_type || nonce || challenge_digest || token_key_id,
+// which keeps Q off the wire just like challenge-bound redemption does.
+
+use sha2::{Digest, Sha256};
+
+use crate::{
+    blind_sig::BlindSignature,
+    bound_token::BoundToken,
+    keys::{KeyId, PublicKey},
+    ticket::Ticket,
+    token::Token,
+    utils::{ristretto_bytes, ristretto_from_bytes, scalar_bytes, scalar_from_bytes, ByteReader},
+};
+
+pub const TOKEN_TYPE: u16 = 0x4d54;
+
+const TOKEN_KEY_SIZE: usize = 96;
+const AUTHENTICATOR_SIZE: usize = 32 + 64;
+const TOKEN_SIZE: usize = 2 + 32 + 32 + 32 + AUTHENTICATOR_SIZE;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrivacyPassChallenge {
+    pub token_type: u16,
+    pub issuer_name: String,
+    // Empty or 32 bytes
+    pub redemption_context: Vec<u8>,
+    pub origin_info: Vec<String>,
+}
+
+pub struct TokenRequest {
+    pub token_type: u16,
+    pub truncated_token_key_id: u8,
+    pub blinded_msg: Ticket,
+}
+
+pub struct TokenResponse {
+    pub blind_sig: BlindSignature,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrivacyPassToken {
+    pub token_type: u16,
+    pub nonce: [u8; 32],
+    pub challenge_digest: [u8; 32],
+    pub token_key_id: KeyId,
+    pub authenticator: [u8; AUTHENTICATOR_SIZE],
+}
+
+// The parameters of a PrivateToken WWW-Authenticate challenge
+#[derive(Debug, PartialEq)]
+pub struct PrivateTokenChallenge {
+    pub challenge: PrivacyPassChallenge,
+    pub token_key: PublicKey,
+    pub max_age: Option<u32>,
+}
+
+impl PrivacyPassChallenge {
+    pub fn new(
+        issuer_name: &str,
+        redemption_context: &[u8],
+        origin_info: &[&str],
+    ) -> PrivacyPassChallenge {
+        PrivacyPassChallenge {
+            token_type: TOKEN_TYPE,
+            issuer_name: issuer_name.to_string(),
+            redemption_context: redemption_context.to_vec(),
+            origin_info: origin_info
+                .iter()
+                .map(|origin| origin.to_string())
+                .collect(),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ()> {
+        let origin_info = self.origin_info.join(",");
+        if self.issuer_name.is_empty()
+            || self.issuer_name.len() > u16::MAX as usize
+            || !(self.redemption_context.is_empty() || self.redemption_context.len() == 32)
+            || origin_info.len() > u16::MAX as usize
+        {
+            return Err(());
+        }
+
+        let mut bytes =
+            Vec::with_capacity(2 + 2 + self.issuer_name.len() + 33 + 2 + origin_info.len());
+        bytes.extend_from_slice(&self.token_type.to_be_bytes());
+        bytes.extend_from_slice(&(self.issuer_name.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(self.issuer_name.as_bytes());
+        bytes.push(self.redemption_context.len() as u8);
+        bytes.extend_from_slice(&self.redemption_context);
+        bytes.extend_from_slice(&(origin_info.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(origin_info.as_bytes());
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<PrivacyPassChallenge, ()> {
+        let mut reader = ByteReader::new(bytes);
+        let token_type = reader.read_u16()?;
+        let issuer_len = reader.read_u16()? as usize;
+        let issuer_name = reader.read_string(issuer_len)?;
+        let context_len = reader.read(1)?[0] as usize;
+        let redemption_context = reader.read(context_len)?.to_vec();
+        let origin_len = reader.read_u16()? as usize;
+        let origin_info = reader.read_string(origin_len)?;
+        reader.finish()?;
+
+        if issuer_name.is_empty() || !(context_len == 0 || context_len == 32) {
+            return Err(());
+        }
+
+        Ok(PrivacyPassChallenge {
+            token_type,
+            issuer_name,
+            redemption_context,
+            origin_info: if origin_info.is_empty() {
+                Vec::new()
+            } else {
+                origin_info.split(',').map(str::to_string).collect()
+            },
+        })
+    }
+
+    pub fn digest(&self) -> Result<[u8; 32], ()> {
+        Ok(Sha256::digest(&self.to_bytes()?).into())
+    }
+}
+
+impl TokenRequest {
+    pub fn create(ticket: Ticket, pk: &PublicKey) -> TokenRequest {
+        TokenRequest {
+            token_type: TOKEN_TYPE,
+            truncated_token_key_id: pk.key_id()[31],
+            blinded_msg: ticket,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(2 + 1 + 32);
+        bytes.extend_from_slice(&self.token_type.to_be_bytes());
+        bytes.push(self.truncated_token_key_id);
+        bytes.extend_from_slice(&self.blinded_msg.to_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<TokenRequest, ()> {
+        let mut reader = ByteReader::new(bytes);
+        let token_type = reader.read_u16()?;
+        let truncated_token_key_id = reader.read(1)?[0];
+        let blinded_msg = Ticket::from_bytes(reader.read(32)?)?;
+        reader.finish()?;
+
+        Ok(TokenRequest {
+            token_type,
+            truncated_token_key_id,
+            blinded_msg,
+        })
+    }
+
+    // Issuer-side check that the request is for this token type and key
+    pub fn matches(&self, pk: &PublicKey) -> bool {
+        self.token_type == TOKEN_TYPE && self.truncated_token_key_id == pk.key_id()[31]
+    }
+}
+
+impl TokenResponse {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.blind_sig.to_bytes()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<TokenResponse, ()> {
+        Ok(TokenResponse {
+            blind_sig: BlindSignature::from_bytes(bytes)?,
+        })
+    }
+}
+
+impl PrivacyPassToken {
+    pub fn create(
+        token: &Token,
+        challenge: &PrivacyPassChallenge,
+        pk: &PublicKey,
+    ) -> Result<PrivacyPassToken, ()> {
+        let mut pp_token = PrivacyPassToken {
+            token_type: challenge.token_type,
+            nonce: scalar_bytes(&token.t),
+            challenge_digest: challenge.digest()?,
+            token_key_id: pk.key_id(),
+            authenticator: [0u8; AUTHENTICATOR_SIZE],
+        };
+
+        let bound = BoundToken::create(token, &pp_token.token_input());
+        pp_token.authenticator[..32].copy_from_slice(&ristretto_bytes(&token.p_big));
+        pp_token.authenticator[32..].copy_from_slice(&bound.mac);
+
+        Ok(pp_token)
+    }
+
+    pub fn token_input(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(2 + 32 * 3);
+        bytes.extend_from_slice(&self.token_type.to_be_bytes());
+        bytes.extend_from_slice(&self.nonce);
+        bytes.extend_from_slice(&self.challenge_digest);
+        bytes.extend_from_slice(&self.token_key_id);
+        bytes
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.token_input();
+        bytes.extend_from_slice(&self.authenticator);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<PrivacyPassToken, ()> {
+        if bytes.len() != TOKEN_SIZE {
+            return Err(());
+        }
+        let mut reader = ByteReader::new(bytes);
+
+        Ok(PrivacyPassToken {
+            token_type: reader.read_u16()?,
+            nonce: reader.read_array()?,
+            challenge_digest: reader.read_array()?,
+            token_key_id: reader.read_array()?,
+            authenticator: reader.read_array()?,
+        })
+    }
+
+    // The BoundToken carried in the authenticator
+    pub(crate) fn bound_token(&self) -> Result<BoundToken, ()> {
+        Ok(BoundToken {
+            t: scalar_from_bytes(&self.nonce)?,
+            p_big: ristretto_from_bytes(&self.authenticator[..32])?,
+            mac: self.authenticator[32..]
+                .try_into()
+                .expect("incorrect size for mac"),
+        })
+    }
+
+    // Authorization: PrivateToken token="..."
+    pub fn to_header_value(&self) -> String {
+        format!(
+            "PrivateToken token=\"{}\"",
+            encode_base64url(&self.to_bytes())
+        )
+    }
+
+    pub fn from_header_value(value: &str) -> Result<PrivacyPassToken, ()> {
+        let params = parse_private_token_params(value)?;
+        let token = find_param(&params, "token").ok_or(())?;
+
+        PrivacyPassToken::from_bytes(&decode_base64url(token)?)
+    }
+}
+
+impl PrivateTokenChallenge {
+    // WWW-Authenticate: PrivateToken challenge="...", token-key="...", max-age=...
+    pub fn to_header_value(&self) -> Result<String, ()> {
+        let mut value = format!(
+            "PrivateToken challenge=\"{}\", token-key=\"{}\"",
+            encode_base64url(&self.challenge.to_bytes()?),
+            encode_base64url(&encode_token_key(&self.token_key))
+        );
+        if let Some(max_age) = self.max_age {
+            value.push_str(&format!(", max-age={}", max_age));
+        }
+        Ok(value)
+    }
+
+    pub fn from_header_value(value: &str) -> Result<PrivateTokenChallenge, ()> {
+        let params = parse_private_token_params(value)?;
+        let challenge = find_param(&params, "challenge").ok_or(())?;
+        let token_key = find_param(&params, "token-key").ok_or(())?;
+        let max_age = match find_param(&params, "max-age") {
+            Some(max_age) => Some(max_age.parse().map_err(|_| ())?),
+            None => None,
+        };
+
+        Ok(PrivateTokenChallenge {
+            challenge: PrivacyPassChallenge::from_bytes(&decode_base64url(challenge)?)?,
+            token_key: decode_token_key(&decode_base64url(token_key)?)?,
+            max_age,
+        })
+    }
+}
+
+pub fn encode_token_key(pk: &PublicKey) -> [u8; TOKEN_KEY_SIZE] {
+    let mut bytes = [0u8; TOKEN_KEY_SIZE];
+    bytes[..32].copy_from_slice(&ristretto_bytes(&pk.z_big));
+    bytes[32..64].copy_from_slice(&ristretto_bytes(&pk.c_big_x));
+    bytes[64..].copy_from_slice(&ristretto_bytes(&pk.c_big_y));
+    bytes
+}
+
+pub fn decode_token_key(bytes: &[u8]) -> Result<PublicKey, ()> {
+    if bytes.len() != TOKEN_KEY_SIZE {
+        return Err(());
+    }
+
+    Ok(PublicKey {
+        z_big: ristretto_from_bytes(&bytes[..32])?,
+        c_big_x: ristretto_from_bytes(&bytes[32..64])?,
+        c_big_y: ristretto_from_bytes(&bytes[64..])?,
+    })
+}
+
+fn encode_base64url(bytes: &[u8]) -> String {
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+// Padding is optional on input
+fn decode_base64url(value: &str) -> Result<Vec<u8>, ()> {
+    base64::decode_config(value.trim_end_matches('='), base64::URL_SAFE_NO_PAD).map_err(|_| ())
+}
+
+// Splits `PrivateToken a="x", b=y` into its auth-params. Values of this scheme
+// are base64url or integers, so they contain neither commas nor quotes.
+fn parse_private_token_params(value: &str) -> Result<Vec<(String, String)>, ()> {
+    let value = value.trim();
+    let (scheme, rest) = value.split_once(' ').ok_or(())?;
+    if !scheme.eq_ignore_ascii_case("PrivateToken") {
+        return Err(());
+    }
+
+    rest.split(',')
+        .map(|param| {
+            let (name, value) = param.trim().split_once('=').ok_or(())?;
+            let value = value.trim();
+            let value = match value.strip_prefix('"') {
+                Some(quoted) => quoted.strip_suffix('"').ok_or(())?,
+                None => value,
+            };
+            Ok((name.trim().to_ascii_lowercase(), value.to_string()))
+        })
+        .collect()
+}
+
+fn find_param<'a>(params: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    params
+        .iter()
+        .find(|(param, _)| param == name)
+        .map(|(_, value)| value.as_str())
+}
@@ -6,23 +6,67 @@ use rand_core::{CryptoRng, RngCore};
 use sha2::{Digest, Sha512};
 
 use crate::{
-    keys::PublicKey, keys::SecretKey, params::PUBLIC_PARAMS, ticket::Ticket,
-    utils::non_zero_scalar, utils::one_scalar, utils::ristretto_bytes, utils::scalar_bytes,
-    utils::zero_scalar,
+    keys::PublicKey, keys::SecretKey, nonce::HedgedNonces, params::PUBLIC_PARAMS, ticket::Ticket,
+    utils::one_scalar, utils::ristretto_bytes, utils::ristretto_from_bytes, utils::scalar_bytes,
+    utils::scalar_from_bytes, utils::zero_scalar,
 };
 
 pub struct Proof {
     pub c_big: RistrettoPoint,
     pub e_zero: Scalar,
     pub e_one: Scalar,
+    pub e_two: Scalar,
     pub a_zero: Scalar,
     pub a_one: Scalar,
+    pub a_two: Scalar,
     pub a_d: Scalar,
     pub a_rho: Scalar,
     pub a_w: Scalar,
 }
 
 impl Proof {
+    pub const SIZE: usize = 32 * 10;
+
+    // C || e_0 || e_1 || e_2 || a_0 || a_1 || a_2 || a_d || a_rho || a_w
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Proof::SIZE);
+        bytes.extend_from_slice(&ristretto_bytes(&self.c_big));
+        for scalar in [
+            &self.e_zero,
+            &self.e_one,
+            &self.e_two,
+            &self.a_zero,
+            &self.a_one,
+            &self.a_two,
+            &self.a_d,
+            &self.a_rho,
+            &self.a_w,
+        ] {
+            bytes.extend_from_slice(&scalar_bytes(scalar));
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Proof, ()> {
+        if bytes.len() != Proof::SIZE {
+            return Err(());
+        }
+        let scalar = |i: usize| scalar_from_bytes(&bytes[32 * i..32 * (i + 1)]);
+
+        Ok(Proof {
+            c_big: ristretto_from_bytes(&bytes[..32])?,
+            e_zero: scalar(1)?,
+            e_one: scalar(2)?,
+            e_two: scalar(3)?,
+            a_zero: scalar(4)?,
+            a_one: scalar(5)?,
+            a_two: scalar(6)?,
+            a_d: scalar(7)?,
+            a_rho: scalar(8)?,
+            a_w: scalar(9)?,
+        })
+    }
+
     pub fn create<R>(
         rng: &mut R,
         sk: &SecretKey,
@@ -37,14 +81,34 @@ impl Proof {
     where
         R: RngCore + CryptoRng,
     {
-        // e_one_minus_b, a_one_minus_b <-- ZZ_p
-        let simulator_scalars = [Scalar::random(rng); 2];
+        // All nonces are hedged from sk, the signing secrets, the transcript
+        // and fresh randomness
+        let mut nonces = HedgedNonces::new(
+            rng,
+            b"MacTok-IssuanceProof",
+            sk,
+            &[scalar_b, d],
+            &[
+                &ristretto_bytes(&pk.z_big),
+                &ristretto_bytes(&pk.c_big_x),
+                &ristretto_bytes(&pk.c_big_y),
+                &t.to_bytes(),
+                &ristretto_bytes(bs_u_big),
+                &ristretto_bytes(bs_v_big),
+                &scalar_bytes(bs_ts),
+            ],
+        );
 
         // r_mu, r_d, r_rho, r_w <-- ZZ_p
-        let commitment_scalars = [Scalar::random(rng); 4];
+        let commitment_scalars = [
+            nonces.scalar(),
+            nonces.scalar(),
+            nonces.scalar(),
+            nonces.scalar(),
+        ];
 
         // mu <-- ZZ_p*
-        let mu = non_zero_scalar(rng);
+        let mu = nonces.non_zero_scalar();
 
         // C <-- b * C_y + mu * H; TODO: do not use condition
         let c_big = (scalar_b * &pk.c_big_y) + (&mu * &PUBLIC_PARAMS.h_big);
@@ -52,11 +116,21 @@ impl Proof {
         // C_b <-- r_mu * H
         let c_big_b = &commitment_scalars[0] * &PUBLIC_PARAMS.h_big;
 
-        // C_one_minus_b <-- a_one_minus_b * H - e_one_minus_b * (C - (1 - b) * C_y)
-        let c_minus_c_y = (&one_scalar() - scalar_b) * &pk.c_big_y;
-        let point = &c_big - &c_minus_c_y;
-        let c_big_one_minus_b =
-            (&simulator_scalars[1] * &PUBLIC_PARAMS.h_big) - (&simulator_scalars[0] * point);
+        // For the other branches j: e_j, a_j <-- ZZ_p and
+        // C_j <-- a_j * H - e_j * (C - j * C_y)
+        let branch_b = branch_index(scalar_b);
+        let mut simulated = [(zero_scalar(), zero_scalar()); 3];
+        let mut branch_commitments = [c_big_b; 3];
+        for j in 0..3 {
+            if j == branch_b {
+                continue;
+            }
+            let e_j = nonces.scalar();
+            let a_j = nonces.scalar();
+            let point = c_big - (Scalar::from(j as u64) * pk.c_big_y);
+            branch_commitments[j] = (&a_j * &PUBLIC_PARAMS.h_big) - (e_j * point);
+            simulated[j] = (e_j, a_j);
+        }
 
         // C_d <-- r_d * U
         let c_big_d = &commitment_scalars[1] * bs_u_big;
@@ -68,18 +142,8 @@ impl Proof {
         // C_w <-- r_d * V + r_w * G;
         let c_big_w = &r_d_v_big + (&commitment_scalars[3] * &PUBLIC_PARAMS.g_big);
 
-        // e <-- Hash(G, H, C_x, C_y, C, C_zero, C_one_minus_b, C_d, C_rho, C_w) % p;
-        let c_big_zero;
-        let c_big_one;
-
-        if scalar_b == &zero_scalar() {
-            //b is zero
-            c_big_zero = c_big_b;
-            c_big_one = c_big_one_minus_b;
-        } else {
-            c_big_one = c_big_b;
-            c_big_zero = c_big_one_minus_b;
-        }
+        // e <-- Hash(G, H, C_x, C_y, C, C_0, C_1, C_2, C_d, C_rho, C_w) % p;
+        let [c_big_zero, c_big_one, c_big_two] = branch_commitments;
 
         let mut hasher = Sha512::new();
         hasher.update(ristretto_bytes(&pk.c_big_x));
@@ -91,6 +155,7 @@ impl Proof {
         hasher.update(ristretto_bytes(&c_big));
         hasher.update(ristretto_bytes(&c_big_zero));
         hasher.update(ristretto_bytes(&c_big_one));
+        hasher.update(ristretto_bytes(&c_big_two));
         hasher.update(ristretto_bytes(&c_big_d));
         hasher.update(ristretto_bytes(&c_big_rho));
         hasher.update(ristretto_bytes(&c_big_w));
@@ -102,8 +167,8 @@ impl Proof {
             .expect("incorrect size for hash");
         let e = Scalar::from_bytes_mod_order_wide(&hash_bytes);
 
-        // e_b <-- e - e_one_minus_b
-        let e_b = &e - &simulator_scalars[0];
+        // e_b <-- e - sum of the simulated e_j
+        let e_b = e - simulated[0].0 - simulated[1].0 - simulated[2].0;
 
         // a_b <-- r_mu + e_b * mu
         let a_b = &commitment_scalars[0] + (&e_b * &mu);
@@ -123,29 +188,17 @@ impl Proof {
         // a_w <-- r_w + e * w
         let a_w = &commitment_scalars[3] + e * &w;
 
-        let e_zero;
-        let e_one;
-        let a_zero;
-        let a_one;
-
-        if scalar_b == &zero_scalar() {
-            e_zero = e_b;
-            e_one = simulator_scalars[0];
-            a_zero = a_b;
-            a_one = simulator_scalars[1];
-        } else {
-            e_one = e_b;
-            e_zero = simulator_scalars[0];
-            a_one = a_b;
-            a_zero = simulator_scalars[1];
-        }
+        simulated[branch_b] = (e_b, a_b);
+        let [(e_zero, a_zero), (e_one, a_one), (e_two, a_two)] = simulated;
 
         let pi = Proof {
             c_big,
             e_zero,
             e_one,
+            e_two,
             a_zero,
             a_one,
+            a_two,
             a_d,
             a_rho,
             a_w,
@@ -154,3 +207,15 @@ impl Proof {
         pi
     }
 }
+
+// Which branch of the OR proof is real: 0 and 1 are the private bit, 2 is a
+// poisoned signature
+fn branch_index(scalar_b: &Scalar) -> usize {
+    if scalar_b == &zero_scalar() {
+        0
+    } else if scalar_b == &one_scalar() {
+        1
+    } else {
+        2
+    }
+}
@@ -0,0 +1,213 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use std::collections::{HashMap, HashSet};
+
+use curve25519_dalek_ng::{ristretto::RistrettoPoint, scalar::Scalar};
+use rand_core::{CryptoRng, RngCore};
+use sha2::Sha512;
+
+use crate::{
+    client_binding::ClientKey,
+    dleq::DleqProof,
+    token::Token,
+    utils::{ristretto_bytes, scalar_bytes},
+};
+
+pub type Pseudonym = [u8; 32];
+
+// Per-origin, per-window generator H_o = HashToGroup(origin, window)
+pub fn origin_generator(origin: &str, window: u64) -> RistrettoPoint {
+    let mut bytes = Vec::with_capacity(32 + origin.len());
+    bytes.extend_from_slice(b"MacTok-RateLimit");
+    bytes.extend_from_slice(&(origin.len() as u16).to_be_bytes());
+    bytes.extend_from_slice(origin.as_bytes());
+    bytes.extend_from_slice(&window.to_be_bytes());
+
+    RistrettoPoint::hash_from_bytes::<Sha512>(&bytes)
+}
+
+// A token issued on a client-bound ticket (Ticket::create_bound, signed with
+// sign_bound_ticket so that k is a registered client key), redeemed at
+// an origin under the pseudonym N = k * H_o. The DLEQ proof shows
+// log_P K_P = log_{H_o} N, and the server only accepts K_P = k * P for the k
+// bound into the ticket, so N is the same for every token of one client at one
+// origin in one window, and unlinkable across origins and windows. As with
+// ClientBoundToken the client key K itself is never sent.
+pub struct RateLimitedToken {
+    pub t: Scalar,
+    pub p_big: RistrettoPoint,
+    pub q_big: RistrettoPoint,
+    pub k_big_p: RistrettoPoint,
+    pub window: u64,
+    pub pseudonym: RistrettoPoint,
+    pub pi: DleqProof,
+}
+
+impl RateLimitedToken {
+    pub fn create<R>(
+        rng: &mut R,
+        token: &Token,
+        client_key: &ClientKey,
+        origin: &str,
+        window: u64,
+        message: &[u8],
+    ) -> RateLimitedToken
+    where
+        R: RngCore + CryptoRng,
+    {
+        let h_big_o = origin_generator(origin, window);
+        let k_big_p = client_key.k * token.p_big;
+        let pseudonym = client_key.k * h_big_o;
+        let context = proof_context(&token.t, &token.q_big, message);
+        let pi = DleqProof::create(
+            rng,
+            &client_key.k,
+            &token.p_big,
+            &k_big_p,
+            &h_big_o,
+            &pseudonym,
+            &context,
+        );
+
+        RateLimitedToken {
+            t: token.t,
+            p_big: token.p_big,
+            q_big: token.q_big,
+            k_big_p,
+            window,
+            pseudonym,
+            pi,
+        }
+    }
+
+    pub(crate) fn verify_pseudonym(&self, origin: &str, message: &[u8]) -> Result<(), ()> {
+        let context = proof_context(&self.t, &self.q_big, message);
+        self.pi.verify(
+            &self.p_big,
+            &self.k_big_p,
+            &origin_generator(origin, self.window),
+            &self.pseudonym,
+            &context,
+        )
+    }
+}
+
+// Counts redemptions per pseudonym and window at one origin
+#[derive(Debug)]
+pub struct RateLimiter {
+    pub origin: String,
+    pub window_length: u64,
+    pub limit: u32,
+    counts: HashMap<(Pseudonym, u64), u32>,
+}
+
+impl RateLimiter {
+    pub fn new(origin: &str, window_length: u64, limit: u32) -> RateLimiter {
+        assert!(window_length > 0, "window length must be positive");
+
+        RateLimiter {
+            origin: origin.to_string(),
+            window_length,
+            limit,
+            counts: HashMap::new(),
+        }
+    }
+
+    pub fn window_at(&self, now: u64) -> u64 {
+        now / self.window_length
+    }
+
+    pub fn count(&self, pseudonym: &Pseudonym, window: u64) -> u32 {
+        self.counts.get(&(*pseudonym, window)).copied().unwrap_or(0)
+    }
+
+    // Returns the new count, or Err once the limit is reached
+    pub(crate) fn record(&mut self, pseudonym: &Pseudonym, window: u64) -> Result<u32, ()> {
+        let count = self.counts.entry((*pseudonym, window)).or_insert(0);
+        if *count >= self.limit {
+            return Err(());
+        }
+        *count += 1;
+
+        Ok(*count)
+    }
+
+    // Drops the counters of windows before the one containing now
+    pub fn prune(&mut self, now: u64) {
+        let current = self.window_at(now);
+        self.counts.retain(|(_, window), _| *window >= current);
+    }
+}
+
+// Issuer side: client-bound tickets are only signed for registered client
+// keys, and at most limit times per key and window. Together with the ticket
+// binding proof this stops a client from minting fresh keys to get a fresh
+// pseudonym at every origin.
+#[derive(Debug)]
+pub struct IssuanceLimiter {
+    pub window_length: u64,
+    pub limit: u32,
+    registered: HashSet<[u8; 32]>,
+    counts: HashMap<([u8; 32], u64), u32>,
+}
+
+impl IssuanceLimiter {
+    pub fn new(window_length: u64, limit: u32) -> IssuanceLimiter {
+        assert!(window_length > 0, "window length must be positive");
+
+        IssuanceLimiter {
+            window_length,
+            limit,
+            registered: HashSet::new(),
+            counts: HashMap::new(),
+        }
+    }
+
+    // Registers an attested client key
+    pub fn register(&mut self, k_big: &RistrettoPoint) {
+        self.registered.insert(ristretto_bytes(k_big));
+    }
+
+    pub fn is_registered(&self, k_big: &RistrettoPoint) -> bool {
+        self.registered.contains(&ristretto_bytes(k_big))
+    }
+
+    pub fn window_at(&self, now: u64) -> u64 {
+        now / self.window_length
+    }
+
+    // Returns the new count, or Err for an unregistered key or once the limit
+    // is reached
+    pub(crate) fn record(&mut self, k_big: &RistrettoPoint, now: u64) -> Result<u32, ()> {
+        if !self.is_registered(k_big) {
+            return Err(());
+        }
+
+        let window = self.window_at(now);
+        let count = self
+            .counts
+            .entry((ristretto_bytes(k_big), window))
+            .or_insert(0);
+        if *count >= self.limit {
+            return Err(());
+        }
+        *count += 1;
+
+        Ok(*count)
+    }
+
+    // Drops the counters of windows before the one containing now
+    pub fn prune(&mut self, now: u64) {
+        let current = self.window_at(now);
+        self.counts.retain(|(_, window), _| *window >= current);
+    }
+}
+
+fn proof_context(t: &Scalar, q_big: &RistrettoPoint, message: &[u8]) -> Vec<u8> {
+    let mut context = Vec::with_capacity(64 + message.len());
+    context.extend_from_slice(&scalar_bytes(t));
+    context.extend_from_slice(&ristretto_bytes(q_big));
+    context.extend_from_slice(message);
+    context
+}
@@ -0,0 +1,184 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use rand_core::{CryptoRng, RngCore};
+
+use crate::{
+    blind_sig::{BlindSignature, PrivateMetadata},
+    keys::{PublicKey, SecretKey},
+    server::{redeem_token, redeem_voprf_token, RedemptionOutcome},
+    ticket::{Receipt, Ticket},
+    token::Token,
+    voprf::{
+        VoprfEvaluation, VoprfPublicKey, VoprfReceipt, VoprfSecretKey, VoprfTicket, VoprfToken,
+    },
+};
+
+// One token construction, seen from the client, the issuer and the redeemer.
+// Callers written against this trait can switch constructions by switching
+// the type parameter.
+pub trait TokenScheme {
+    type SecretKey;
+    type PublicKey;
+    // What the issuer embeds in a token besides its validity
+    type Metadata;
+    type Request;
+    // What the client keeps between sending a request and finalizing it
+    type ClientState;
+    type Response;
+    type Token;
+    type Outcome;
+
+    fn create_keys<R>(rng: &mut R) -> (Self::SecretKey, Self::PublicKey)
+    where
+        R: RngCore + CryptoRng;
+
+    fn request<R>(
+        rng: &mut R,
+        pk: &Self::PublicKey,
+    ) -> Result<(Self::Request, Self::ClientState), ()>
+    where
+        R: RngCore + CryptoRng;
+
+    fn issue<R>(
+        rng: &mut R,
+        sk: &Self::SecretKey,
+        pk: &Self::PublicKey,
+        request: &Self::Request,
+        metadata: Self::Metadata,
+    ) -> Self::Response
+    where
+        R: RngCore + CryptoRng;
+
+    fn finalize<R>(
+        rng: &mut R,
+        pk: &Self::PublicKey,
+        request: &Self::Request,
+        state: Self::ClientState,
+        response: &Self::Response,
+    ) -> Result<Self::Token, ()>
+    where
+        R: RngCore + CryptoRng;
+
+    fn redeem(sk: &Self::SecretKey, token: &Self::Token) -> Result<Self::Outcome, ()>;
+}
+
+// Tokens with a private metadata bit (or poison), keyed-verification MACs
+pub struct PrivateBitScheme;
+
+// RFC 9497 VOPRF tokens without metadata
+pub struct VoprfScheme;
+
+impl TokenScheme for PrivateBitScheme {
+    type SecretKey = SecretKey;
+    type PublicKey = PublicKey;
+    type Metadata = PrivateMetadata;
+    type Request = Ticket;
+    type ClientState = Receipt;
+    type Response = BlindSignature;
+    type Token = Token;
+    type Outcome = RedemptionOutcome;
+
+    fn create_keys<R>(rng: &mut R) -> (SecretKey, PublicKey)
+    where
+        R: RngCore + CryptoRng,
+    {
+        let sk = SecretKey::create(rng);
+        let pk = PublicKey::create(&sk);
+        (sk, pk)
+    }
+
+    fn request<R>(rng: &mut R, pk: &PublicKey) -> Result<(Ticket, Receipt), ()>
+    where
+        R: RngCore + CryptoRng,
+    {
+        Ok(Ticket::create(rng, pk))
+    }
+
+    fn issue<R>(
+        rng: &mut R,
+        sk: &SecretKey,
+        pk: &PublicKey,
+        request: &Ticket,
+        metadata: PrivateMetadata,
+    ) -> BlindSignature
+    where
+        R: RngCore + CryptoRng,
+    {
+        BlindSignature::create_with_metadata(rng, pk, sk, request, metadata)
+    }
+
+    fn finalize<R>(
+        rng: &mut R,
+        pk: &PublicKey,
+        request: &Ticket,
+        state: Receipt,
+        response: &BlindSignature,
+    ) -> Result<Token, ()>
+    where
+        R: RngCore + CryptoRng,
+    {
+        Token::create(rng, pk, response, request, &state)
+    }
+
+    fn redeem(sk: &SecretKey, token: &Token) -> Result<RedemptionOutcome, ()> {
+        Ok(redeem_token(token, sk))
+    }
+}
+
+impl TokenScheme for VoprfScheme {
+    type SecretKey = VoprfSecretKey;
+    type PublicKey = VoprfPublicKey;
+    type Metadata = ();
+    type Request = VoprfTicket;
+    type ClientState = VoprfReceipt;
+    type Response = VoprfEvaluation;
+    type Token = VoprfToken;
+    type Outcome = ();
+
+    fn create_keys<R>(rng: &mut R) -> (VoprfSecretKey, VoprfPublicKey)
+    where
+        R: RngCore + CryptoRng,
+    {
+        let sk = VoprfSecretKey::create(rng);
+        let pk = VoprfPublicKey::create(&sk);
+        (sk, pk)
+    }
+
+    fn request<R>(rng: &mut R, _pk: &VoprfPublicKey) -> Result<(VoprfTicket, VoprfReceipt), ()>
+    where
+        R: RngCore + CryptoRng,
+    {
+        VoprfTicket::create(rng)
+    }
+
+    fn issue<R>(
+        rng: &mut R,
+        sk: &VoprfSecretKey,
+        pk: &VoprfPublicKey,
+        request: &VoprfTicket,
+        _metadata: (),
+    ) -> VoprfEvaluation
+    where
+        R: RngCore + CryptoRng,
+    {
+        VoprfEvaluation::create(rng, sk, pk, request)
+    }
+
+    fn finalize<R>(
+        _rng: &mut R,
+        pk: &VoprfPublicKey,
+        request: &VoprfTicket,
+        state: VoprfReceipt,
+        response: &VoprfEvaluation,
+    ) -> Result<VoprfToken, ()>
+    where
+        R: RngCore + CryptoRng,
+    {
+        VoprfToken::create(pk, request, &state, response)
+    }
+
+    fn redeem(sk: &VoprfSecretKey, token: &VoprfToken) -> Result<(), ()> {
+        redeem_voprf_token(token, sk)
+    }
+}
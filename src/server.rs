@@ -1,16 +1,290 @@
 // Copyright (c) Microsoft Corporation. All rights reserved.
 // Licensed under the MIT license.
 
-use crate::{keys::SecretKey, token::Token};
+use curve25519_dalek_ng::{ristretto::RistrettoPoint, scalar::Scalar, traits::Identity};
+use rand_core::{CryptoRng, RngCore};
 
-pub fn redeem_token(token: &Token, sk: &SecretKey) -> Result<bool, ()> {
-    let false_scalar = &sk.x + (&token.t * &sk.z);
+use crate::{
+    blind_sig::{BlindSignature, PrivateMetadata},
+    bound_token::BoundToken,
+    challenge::{ChallengeRegistry, ChallengeToken},
+    client_binding::{BindingPublicKey, BindingSecretKey, ClientBoundToken, TicketBindingProof},
+    epoch::{EpochSecretKey, EpochSpentTokens, EpochToken},
+    issuance::{IssuanceRequest, IssuanceResponse},
+    key_manager::KeyManager,
+    keys::{KeyId, PublicKey, SecretKey},
+    policy::{IssuanceContext, IssuanceDecision, IssuancePolicy},
+    presentation::TokenPresentation,
+    privacy_pass::{PrivacyPassChallenge, PrivacyPassToken},
+    rate_limit::{IssuanceLimiter, Pseudonym, RateLimitedToken, RateLimiter},
+    signer::{KeyOperations, MacVerdict},
+    spent_tokens::SpentTokens,
+    split_redemption::{BlindedToken, Candidates, FrontEndKey},
+    ticket::Ticket,
+    token::Token,
+    voprf::{VoprfSecretKey, VoprfToken},
+};
+
+// The two possible MAC values (x + t * z) * P and (x + y + t * z) * P
+fn mac_candidates(
+    t: &Scalar,
+    p_big: &RistrettoPoint,
+    sk: &SecretKey,
+) -> (RistrettoPoint, RistrettoPoint) {
+    let false_scalar = sk.x + (t * sk.z);
     let true_scalar = &false_scalar + &sk.y;
-    let false_point = &false_scalar * &token.p_big;
-    let true_point = &true_scalar * &token.p_big;
 
-    let is_true = &true_point == &token.q_big;
-    let is_false = &false_point == &token.q_big;
+    (false_scalar * p_big, true_scalar * p_big)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RedemptionOutcome {
+    Valid(bool),
+    // A poisoned token, issued with PrivateMetadata::Poison
+    Poisoned,
+    // No MAC candidate matched: a forgery or a token for another key
+    Invalid,
+    // A valid or poisoned token that was redeemed before
+    DoubleSpent,
+    // The token's epoch has ended or not started yet
+    ExpiredEpoch,
+    UnknownKey,
+    // P is the identity, or more than one candidate matched (a degenerate key)
+    Malformed,
+}
+
+// A redemption outcome with the key and epoch it was decided under, where known
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RedemptionReport {
+    pub key_id: Option<KeyId>,
+    pub epoch: Option<u32>,
+    pub outcome: RedemptionOutcome,
+}
+
+// Result of spending several tokens on one action
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiRedemption {
+    // true only if every token carried b = true
+    pub all_true: bool,
+    pub bits: Vec<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitedRedemption {
+    pub b: bool,
+    pub pseudonym: Pseudonym,
+    // Redemptions under this pseudonym in the current window, this one included
+    pub count: u32,
+}
+
+impl RedemptionOutcome {
+    // The bit of a valid token; every other outcome is an error
+    pub fn bit(&self) -> Result<bool, ()> {
+        match self {
+            RedemptionOutcome::Valid(b) => Ok(*b),
+            _ => Err(()),
+        }
+    }
+}
+
+// Tells valid, poisoned and forged tokens apart. Checks the MAC only; see
+// redeem_token_report for spending.
+pub fn redeem_token(token: &Token, sk: &SecretKey) -> RedemptionOutcome {
+    if token.p_big == RistrettoPoint::identity() {
+        return RedemptionOutcome::Malformed;
+    }
+
+    let (false_point, true_point) = mac_candidates(&token.t, &token.p_big, sk);
+    // (x + 2 * y + t * z) * P
+    let poison_point = true_point + (sk.y * token.p_big);
+
+    match (
+        false_point == token.q_big,
+        true_point == token.q_big,
+        poison_point == token.q_big,
+    ) {
+        (true, false, false) => RedemptionOutcome::Valid(false),
+        (false, true, false) => RedemptionOutcome::Valid(true),
+        (false, false, true) => RedemptionOutcome::Poisoned,
+        (false, false, false) => RedemptionOutcome::Invalid,
+        _ => RedemptionOutcome::Malformed,
+    }
+}
+
+// redeem_token for a key held behind KeyOperations, e.g. in a signer process.
+// The signer only reports its verdict on the MAC.
+pub fn redeem_token_with_key_ops<K: KeyOperations>(token: &Token, ops: &K) -> RedemptionOutcome {
+    match ops.verify_mac(&token.t, &token.p_big, &token.q_big) {
+        Ok(MacVerdict::Bit(b)) => RedemptionOutcome::Valid(b),
+        Ok(MacVerdict::Poison) => RedemptionOutcome::Poisoned,
+        Ok(MacVerdict::Invalid) => RedemptionOutcome::Invalid,
+        Err(()) => RedemptionOutcome::Malformed,
+    }
+}
+
+// Redemption for tokens without private metadata: valid or not
+pub fn redeem_voprf_token(token: &VoprfToken, sk: &VoprfSecretKey) -> Result<(), ()> {
+    if !token.verify(sk) {
+        return Err(());
+    }
+
+    Ok(())
+}
+
+// Classifies token and spends it if its MAC is good. Only such tokens are
+// reported as double-spent, so the report reveals nothing about others.
+pub fn redeem_token_report(
+    token: &Token,
+    pk: &PublicKey,
+    sk: &SecretKey,
+    spent: &mut SpentTokens,
+) -> RedemptionReport {
+    let mut outcome = redeem_token(token, sk);
+    if has_good_mac(&outcome) && !spent.mark_spent(&token.t) {
+        outcome = RedemptionOutcome::DoubleSpent;
+    }
+
+    RedemptionReport {
+        key_id: Some(pk.key_id()),
+        epoch: None,
+        outcome,
+    }
+}
+
+pub(crate) fn has_good_mac(outcome: &RedemptionOutcome) -> bool {
+    matches!(
+        outcome,
+        RedemptionOutcome::Valid(_) | RedemptionOutcome::Poisoned
+    )
+}
+
+// redeem_managed_token with every failure told apart
+pub fn redeem_managed_token_report(
+    token: &EpochToken,
+    keys: &KeyManager,
+    spent: &mut EpochSpentTokens,
+    now: u64,
+) -> RedemptionReport {
+    let mut report = RedemptionReport {
+        key_id: None,
+        epoch: Some(token.epoch),
+        outcome: RedemptionOutcome::UnknownKey,
+    };
+    let managed = match keys.keys().iter().find(|k| k.key.epoch.id == token.epoch) {
+        Some(managed) => managed,
+        None => return report,
+    };
+    report.key_id = Some(managed.key_id);
+
+    if keys.redemption_key(token.epoch, now).is_none() {
+        report.outcome = RedemptionOutcome::ExpiredEpoch;
+        return report;
+    }
+
+    report.outcome = redeem_token(&token.token, &managed.key.sk);
+    if has_good_mac(&report.outcome) && !spent.mark_spent(&managed.key.epoch, &token.token.t) {
+        report.outcome = RedemptionOutcome::DoubleSpent;
+    }
+    report
+}
+
+pub fn redeem_epoch_token(
+    token: &EpochToken,
+    esk: &EpochSecretKey,
+    spent: &mut EpochSpentTokens,
+    now: u64,
+) -> Result<bool, ()> {
+    if token.epoch != esk.epoch.id || !esk.epoch.is_valid_at(now) {
+        return Err(());
+    }
+
+    let b = redeem_token(&token.token, &esk.sk).bit()?;
+
+    if !spent.mark_spent(&esk.epoch, &token.token.t) {
+        return Err(());
+    }
+
+    Ok(b)
+}
+
+pub fn redeem_managed_token(
+    token: &EpochToken,
+    keys: &KeyManager,
+    spent: &mut EpochSpentTokens,
+    now: u64,
+) -> Result<bool, ()> {
+    let esk = keys.redemption_key(token.epoch, now).ok_or(())?;
+
+    redeem_epoch_token(token, esk, spent, now)
+}
+
+pub fn redeem_bound_token(token: &BoundToken, message: &[u8], sk: &SecretKey) -> Result<bool, ()> {
+    let (false_point, true_point) = mac_candidates(&token.t, &token.p_big, sk);
+
+    let is_true = token.verify(&true_point, message);
+    let is_false = token.verify(&false_point, message);
+
+    if !(is_true ^ is_false) {
+        return Err(());
+    }
+
+    Ok(is_true)
+}
+
+// Fails for unknown or expired challenges, and for tokens already redeemed
+// against the challenge
+pub fn redeem_challenge_token(
+    token: &ChallengeToken,
+    challenges: &mut ChallengeRegistry,
+    sk: &SecretKey,
+    now: u64,
+) -> Result<bool, ()> {
+    let challenge = challenges.get(&token.challenge_digest, now).ok_or(())?;
+    if challenge.origin != challenges.origin || challenge.key_id != PublicKey::create(sk).key_id() {
+        return Err(());
+    }
+
+    let b = redeem_bound_token(&token.token, &challenge.to_bytes(), sk)?;
+    challenges.consume(&token.challenge_digest, &token.token.t)?;
+
+    Ok(b)
+}
+
+// Origin-side redemption of a Privacy Pass Token against the challenge the
+// origin issued for it
+pub fn redeem_privacy_pass_token(
+    token: &PrivacyPassToken,
+    challenge: &PrivacyPassChallenge,
+    sk: &SecretKey,
+) -> Result<bool, ()> {
+    if token.token_type != challenge.token_type
+        || token.challenge_digest != challenge.digest()?
+        || token.token_key_id != PublicKey::create(sk).key_id()
+    {
+        return Err(());
+    }
+
+    redeem_bound_token(&token.bound_token()?, &token.token_input(), sk)
+}
+
+pub fn redeem_client_bound_token(
+    token: &ClientBoundToken,
+    message: &[u8],
+    sk: &SecretKey,
+    bsk: &BindingSecretKey,
+) -> Result<bool, ()> {
+    // With K_P the identity, any token would pass as bound to the zero key
+    if token.p_big == RistrettoPoint::identity() || token.k_big_p == RistrettoPoint::identity() {
+        return Err(());
+    }
+    token.verify_possession(message)?;
+
+    // Q carries the extra k * w * P term from the client-bound ticket
+    let client_term = bsk.w * token.k_big_p;
+    let (false_point, true_point) = mac_candidates(&token.t, &token.p_big, sk);
+
+    let is_true = (true_point + client_term) == token.q_big;
+    let is_false = (false_point + client_term) == token.q_big;
 
     if !(is_true ^ is_false) {
         return Err(());
@@ -18,3 +292,271 @@ pub fn redeem_token(token: &Token, sk: &SecretKey) -> Result<bool, ()> {
 
     Ok(is_true)
 }
+
+// Spends exactly expected tokens on one action. All tokens are checked before
+// any is marked spent, so a failure leaves the spent set untouched.
+pub fn redeem_tokens(
+    tokens: &[Token],
+    expected: usize,
+    sk: &SecretKey,
+    spent: &mut SpentTokens,
+) -> Result<MultiRedemption, ()> {
+    if tokens.is_empty() || tokens.len() != expected {
+        return Err(());
+    }
+
+    let mut seen = SpentTokens::new();
+    let mut bits = Vec::with_capacity(tokens.len());
+    for token in tokens {
+        if spent.is_spent(&token.t) || !seen.mark_spent(&token.t) {
+            return Err(());
+        }
+        bits.push(redeem_token(token, sk).bit()?);
+    }
+
+    for token in tokens {
+        spent.mark_spent(&token.t);
+    }
+
+    Ok(MultiRedemption {
+        all_true: bits.iter().all(|b| *b),
+        bits,
+    })
+}
+
+// Server handler for an issuance request. The caller fills in context with
+// the headers, risk score and quota it has for the request; the attestation
+// always comes from the request's proof, checked by verify_proof.
+pub fn handle_issuance_request<R, P, V>(
+    rng: &mut R,
+    request: &[u8],
+    context: IssuanceContext,
+    pk: &PublicKey,
+    sk: &SecretKey,
+    policy: &P,
+    verify_proof: V,
+) -> Result<Vec<u8>, ()>
+where
+    R: RngCore + CryptoRng,
+    P: IssuancePolicy,
+    V: FnOnce(&[u8]) -> bool,
+{
+    let request = IssuanceRequest::from_bytes(request)?;
+    if request.key_id != pk.key_id() {
+        return Err(());
+    }
+    let context = context.with_attestation(&request, verify_proof);
+    match policy.decide(&context) {
+        IssuanceDecision::Issue(metadata) => {
+            Ok(sign_issuance_request(rng, &request, pk, sk, metadata).to_bytes())
+        }
+        IssuanceDecision::Refuse => Err(()),
+    }
+}
+
+pub(crate) fn sign_issuance_request<R>(
+    rng: &mut R,
+    request: &IssuanceRequest,
+    pk: &PublicKey,
+    sk: &SecretKey,
+    metadata: PrivateMetadata,
+) -> IssuanceResponse
+where
+    R: RngCore + CryptoRng,
+{
+    IssuanceResponse {
+        version: request.version,
+        key_id: request.key_id,
+        nonce: request.nonce,
+        signatures: request
+            .tickets
+            .iter()
+            .map(|ticket| BlindSignature::create_with_metadata(rng, pk, sk, ticket, metadata))
+            .collect(),
+    }
+}
+
+// Signs every ticket in request with a key held behind KeyOperations
+pub fn sign_issuance_request_with_key_ops<K: KeyOperations>(
+    request: &IssuanceRequest,
+    ops: &K,
+    metadata: PrivateMetadata,
+) -> Result<IssuanceResponse, ()> {
+    Ok(IssuanceResponse {
+        version: request.version,
+        key_id: request.key_id,
+        nonce: request.nonce,
+        signatures: request
+            .tickets
+            .iter()
+            .map(|ticket| ops.sign(ticket, metadata))
+            .collect::<Result<_, ()>>()?,
+    })
+}
+
+// Signs ticket with whatever policy decides for context
+pub fn issue_with_policy<R, P>(
+    rng: &mut R,
+    ticket: &Ticket,
+    pk: &PublicKey,
+    sk: &SecretKey,
+    policy: &P,
+    context: &IssuanceContext,
+) -> Result<BlindSignature, ()>
+where
+    R: RngCore + CryptoRng,
+    P: IssuancePolicy,
+{
+    match policy.decide(context) {
+        IssuanceDecision::Issue(metadata) => Ok(BlindSignature::create_with_metadata(
+            rng, pk, sk, ticket, metadata,
+        )),
+        IssuanceDecision::Refuse => Err(()),
+    }
+}
+
+// Redeems token and signs a fresh ticket in one step. policy maps the old bit
+// to the new one, so a bit can move between sessions without linking them.
+pub fn exchange_token<R, F>(
+    rng: &mut R,
+    token: &Token,
+    ticket: &Ticket,
+    pk: &PublicKey,
+    sk: &SecretKey,
+    spent: &mut SpentTokens,
+    policy: F,
+) -> Result<BlindSignature, ()>
+where
+    R: RngCore + CryptoRng,
+    F: FnOnce(bool) -> bool,
+{
+    if spent.is_spent(&token.t) {
+        return Err(());
+    }
+
+    let b = redeem_token(token, sk).bit()?;
+    let bs = BlindSignature::create(rng, pk, sk, ticket, policy(b));
+
+    if !spent.mark_spent(&token.t) {
+        return Err(());
+    }
+
+    Ok(bs)
+}
+
+// Multi-show redemption: nothing is marked spent, since repeated presentations
+// of the same token are unlinkable by design
+pub fn redeem_presentation(
+    presentation: &TokenPresentation,
+    context: &[u8],
+    sk: &SecretKey,
+) -> Result<bool, ()> {
+    presentation.verify_proof(&PublicKey::create(sk), context)?;
+
+    // (x + b * y) * P' + z * C_t - C_q
+    let false_point =
+        (sk.x * presentation.p_big) + (sk.z * presentation.c_big_t) - presentation.c_big_q;
+    let true_point = false_point + (sk.y * presentation.p_big);
+
+    let is_true = true_point == presentation.v_big;
+    let is_false = false_point == presentation.v_big;
+
+    if !(is_true ^ is_false) {
+        return Err(());
+    }
+
+    Ok(is_true)
+}
+
+// Signs a client-bound ticket after checking that it embeds the registered
+// client key K, counting the issuance against K
+#[allow(clippy::too_many_arguments)]
+pub fn sign_bound_ticket<R>(
+    rng: &mut R,
+    ticket: &Ticket,
+    pi: &TicketBindingProof,
+    k_big: &RistrettoPoint,
+    pk: &PublicKey,
+    sk: &SecretKey,
+    bpk: &BindingPublicKey,
+    limiter: &mut IssuanceLimiter,
+    now: u64,
+    b: bool,
+) -> Result<BlindSignature, ()>
+where
+    R: RngCore + CryptoRng,
+{
+    if !limiter.is_registered(k_big) {
+        return Err(());
+    }
+    pi.verify(pk, bpk, k_big, ticket)?;
+    limiter.record(k_big, now)?;
+
+    Ok(BlindSignature::create(rng, pk, sk, ticket, b))
+}
+
+pub fn redeem_rate_limited_token(
+    token: &RateLimitedToken,
+    message: &[u8],
+    sk: &SecretKey,
+    bsk: &BindingSecretKey,
+    limiter: &mut RateLimiter,
+    spent: &mut SpentTokens,
+    now: u64,
+) -> Result<RateLimitedRedemption, ()> {
+    if token.window != limiter.window_at(now) || spent.is_spent(&token.t) {
+        return Err(());
+    }
+    token.verify_pseudonym(&limiter.origin, message)?;
+
+    // As for client-bound tokens, Q carries k * w * P
+    let client_term = bsk.w * token.k_big_p;
+    let (false_point, true_point) = mac_candidates(&token.t, &token.p_big, sk);
+
+    let is_true = (true_point + client_term) == token.q_big;
+    let is_false = (false_point + client_term) == token.q_big;
+
+    if !(is_true ^ is_false) {
+        return Err(());
+    }
+
+    let pseudonym = token.pseudonym.compress().to_bytes();
+    let count = limiter.record(&pseudonym, token.window)?;
+    spent.mark_spent(&token.t);
+
+    Ok(RateLimitedRedemption {
+        b: is_true,
+        pseudonym,
+        count,
+    })
+}
+
+// Front-end side of a split redemption. back_end forwards the blinded token to
+// the back-end and returns its candidates; the front-end checks the token
+// against them but never learns the bit.
+pub fn redeem_token_split<R, F>(
+    rng: &mut R,
+    token: &Token,
+    front_end: &FrontEndKey,
+    spent: &mut SpentTokens,
+    back_end: F,
+) -> Result<(), ()>
+where
+    R: RngCore + CryptoRng,
+    F: FnOnce(&BlindedToken) -> Result<Candidates, ()>,
+{
+    if spent.is_spent(&token.t) {
+        return Err(());
+    }
+
+    let blinded = front_end.blind(rng, token)?;
+    if !back_end(&blinded)?.contains(&blinded.r_big) {
+        return Err(());
+    }
+
+    if !spent.mark_spent(&token.t) {
+        return Err(());
+    }
+
+    Ok(())
+}
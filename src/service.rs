@@ -0,0 +1,162 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+// Async issuance and redemption for tokio services. Key lookups, spent-store
+// updates and policy decisions are awaited; signing and MAC checks run on the
+// blocking pool so they never stall the executor. Synchronous policies work
+// as they are; async ones can look up risk scores or quotas before deciding.
+// Keys are only reached through KeyOperations, so they can stay in a signer
+// process.
+
+use std::{collections::HashMap, sync::Arc, sync::Mutex};
+
+use async_trait::async_trait;
+use curve25519_dalek_ng::scalar::Scalar;
+use tokio::task::spawn_blocking;
+
+use crate::{
+    issuance::IssuanceRequest,
+    keys::KeyId,
+    policy::{IssuanceContext, IssuanceDecision, IssuancePolicy},
+    server::{
+        has_good_mac, redeem_token_with_key_ops, sign_issuance_request_with_key_ops,
+        RedemptionOutcome, RedemptionReport,
+    },
+    signer::KeyOperations,
+    spent_tokens::SpentTokens,
+    token::Token,
+};
+
+#[async_trait]
+pub trait AsyncKeyProvider: Send + Sync {
+    type Key: KeyOperations + Send + Sync + 'static;
+
+    // Fails for unknown keys and keys that may not be used right now
+    async fn key(&self, key_id: &KeyId) -> Result<Arc<Self::Key>, ()>;
+}
+
+#[async_trait]
+pub trait AsyncSpentStore: Send + Sync {
+    // Records t as spent; false if it already was. Must be atomic.
+    async fn mark_spent(&self, t: &Scalar) -> Result<bool, ()>;
+}
+
+#[async_trait]
+pub trait AsyncIssuancePolicy: Send + Sync {
+    async fn decide(&self, context: &IssuanceContext) -> IssuanceDecision;
+}
+
+#[async_trait]
+pub trait AsyncIssuer {
+    // Takes an encoded IssuanceRequest and the headers it came with and
+    // returns an encoded IssuanceResponse
+    async fn issue(&self, request: &[u8], headers: &[(&str, &str)]) -> Result<Vec<u8>, ()>;
+}
+
+#[async_trait]
+pub trait AsyncRedeemer {
+    // Takes an encoded Token, as received from the client. Fails only when
+    // no decision could be made, e.g. when the spent store is unreachable.
+    async fn redeem(&self, key_id: &KeyId, token: &[u8]) -> Result<RedemptionReport, ()>;
+}
+
+pub struct TokenService<K, S, P, V> {
+    pub keys: K,
+    pub spent: S,
+    pub policy: P,
+    // Checks the proof attached to an issuance request
+    pub verify_proof: V,
+}
+
+#[async_trait]
+impl<K, S, P, V> AsyncIssuer for TokenService<K, S, P, V>
+where
+    K: AsyncKeyProvider,
+    S: AsyncSpentStore,
+    P: AsyncIssuancePolicy,
+    V: Fn(&[u8]) -> bool + Send + Sync,
+{
+    async fn issue(&self, request: &[u8], headers: &[(&str, &str)]) -> Result<Vec<u8>, ()> {
+        let request = IssuanceRequest::from_bytes(request)?;
+        let key = self.keys.key(&request.key_id).await?;
+        let context = IssuanceContext::from_request(&request, headers, &self.verify_proof);
+        let metadata = match self.policy.decide(&context).await {
+            IssuanceDecision::Issue(metadata) => metadata,
+            IssuanceDecision::Refuse => return Err(()),
+        };
+
+        spawn_blocking(move || {
+            Ok(sign_issuance_request_with_key_ops(&request, &*key, metadata)?.to_bytes())
+        })
+        .await
+        .map_err(|_| ())?
+    }
+}
+
+#[async_trait]
+impl<K, S, P, V> AsyncRedeemer for TokenService<K, S, P, V>
+where
+    K: AsyncKeyProvider,
+    S: AsyncSpentStore,
+    P: AsyncIssuancePolicy,
+    V: Fn(&[u8]) -> bool + Send + Sync,
+{
+    // The token is only marked spent once its MAC has checked out
+    async fn redeem(&self, key_id: &KeyId, token: &[u8]) -> Result<RedemptionReport, ()> {
+        let mut report = RedemptionReport {
+            key_id: Some(*key_id),
+            epoch: None,
+            outcome: RedemptionOutcome::Malformed,
+        };
+        let token = match Token::from_bytes(token) {
+            Ok(token) => token,
+            Err(()) => return Ok(report),
+        };
+        let key = match self.keys.key(key_id).await {
+            Ok(key) => key,
+            Err(()) => {
+                report.outcome = RedemptionOutcome::UnknownKey;
+                return Ok(report);
+            }
+        };
+
+        let t = token.t;
+        report.outcome = spawn_blocking(move || redeem_token_with_key_ops(&token, &*key))
+            .await
+            .map_err(|_| ())?;
+        if has_good_mac(&report.outcome) && !self.spent.mark_spent(&t).await? {
+            report.outcome = RedemptionOutcome::DoubleSpent;
+        }
+        Ok(report)
+    }
+}
+
+#[async_trait]
+impl<O> AsyncKeyProvider for HashMap<KeyId, Arc<O>>
+where
+    O: KeyOperations + Send + Sync + 'static,
+{
+    type Key = O;
+
+    async fn key(&self, key_id: &KeyId) -> Result<Arc<O>, ()> {
+        self.get(key_id).cloned().ok_or(())
+    }
+}
+
+#[async_trait]
+impl AsyncSpentStore for Mutex<SpentTokens> {
+    async fn mark_spent(&self, t: &Scalar) -> Result<bool, ()> {
+        Ok(self.lock().map_err(|_| ())?.mark_spent(t))
+    }
+}
+
+// Synchronous policies, e.g. a RulePolicy or a closure
+#[async_trait]
+impl<P> AsyncIssuancePolicy for P
+where
+    P: IssuancePolicy + Send + Sync,
+{
+    async fn decide(&self, context: &IssuanceContext) -> IssuanceDecision {
+        IssuancePolicy::decide(self, context)
+    }
+}
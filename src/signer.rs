@@ -0,0 +1,266 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+// The operations that need the secret key, so the key can live in a separate
+// signer process. Over a Unix domain socket every message is framed as
+// uint32 length || body:
+//
+//   sign request   uint8 1, T, uint8 metadata (0, 1, or 2 for poison)
+//   mac request    uint8 2, t, P, Q
+//   response       uint8 status (0 ok), BlindSignature or uint8 verdict
+//                  (0, 1, 2 for poison, 3 for invalid)
+//
+// The signer only ever returns the verdict on a MAC, never a point computed
+// with the key, so a caller cannot use it to learn X, Y or Z.
+
+use std::io::{self, Read, Write};
+#[cfg(unix)]
+use std::{
+    os::unix::net::{UnixListener, UnixStream},
+    path::Path,
+    sync::Mutex,
+};
+
+use curve25519_dalek_ng::{ristretto::RistrettoPoint, scalar::Scalar, traits::Identity};
+use rand_core::OsRng;
+
+use crate::{
+    blind_sig::{BlindSignature, PrivateMetadata},
+    keys::{PublicKey, SecretKey},
+    ticket::Ticket,
+    utils::{ristretto_bytes, ristretto_from_bytes, scalar_bytes, scalar_from_bytes, ByteReader},
+};
+
+const OP_SIGN: u8 = 1;
+const OP_MAC: u8 = 2;
+const STATUS_OK: u8 = 0;
+const STATUS_ERROR: u8 = 1;
+const MAX_FRAME: usize = 1 + BlindSignature::SIZE;
+
+// The outcome of checking Q against the MAC values for bit 0, bit 1 and poison
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MacVerdict {
+    Bit(bool),
+    Poison,
+    Invalid,
+}
+
+pub trait KeyOperations {
+    // U, V, ts and the issuance proof for ticket, with fresh randomness
+    fn sign(&self, ticket: &Ticket, metadata: PrivateMetadata) -> Result<BlindSignature, ()>;
+
+    // Checks Q = (x + j * y + t * z) * P for j = 0, 1, 2. Fails for P = identity
+    // and for degenerate keys where more than one j matches.
+    fn verify_mac(
+        &self,
+        t: &Scalar,
+        p_big: &RistrettoPoint,
+        q_big: &RistrettoPoint,
+    ) -> Result<MacVerdict, ()>;
+}
+
+pub struct LocalKeyOperations {
+    pub sk: SecretKey,
+    pub pk: PublicKey,
+}
+
+impl LocalKeyOperations {
+    pub fn new(sk: SecretKey) -> LocalKeyOperations {
+        LocalKeyOperations {
+            pk: PublicKey::create(&sk),
+            sk,
+        }
+    }
+}
+
+impl KeyOperations for LocalKeyOperations {
+    fn sign(&self, ticket: &Ticket, metadata: PrivateMetadata) -> Result<BlindSignature, ()> {
+        Ok(BlindSignature::create_with_metadata(
+            &mut OsRng, &self.pk, &self.sk, ticket, metadata,
+        ))
+    }
+
+    fn verify_mac(
+        &self,
+        t: &Scalar,
+        p_big: &RistrettoPoint,
+        q_big: &RistrettoPoint,
+    ) -> Result<MacVerdict, ()> {
+        if p_big == &RistrettoPoint::identity() {
+            return Err(());
+        }
+
+        let false_point = (self.sk.x + (t * self.sk.z)) * p_big;
+        let y_p_big = self.sk.y * p_big;
+        let true_point = false_point + y_p_big;
+        let poison_point = true_point + y_p_big;
+
+        match (
+            &false_point == q_big,
+            &true_point == q_big,
+            &poison_point == q_big,
+        ) {
+            (true, false, false) => Ok(MacVerdict::Bit(false)),
+            (false, true, false) => Ok(MacVerdict::Bit(true)),
+            (false, false, true) => Ok(MacVerdict::Poison),
+            (false, false, false) => Ok(MacVerdict::Invalid),
+            _ => Err(()),
+        }
+    }
+}
+
+// Client for a signer daemon; one request at a time over one connection
+#[cfg(unix)]
+pub struct UnixKeyOperations {
+    stream: Mutex<UnixStream>,
+}
+
+#[cfg(unix)]
+impl UnixKeyOperations {
+    pub fn connect<P: AsRef<Path>>(path: P) -> io::Result<UnixKeyOperations> {
+        Ok(UnixKeyOperations::from_stream(UnixStream::connect(path)?))
+    }
+
+    pub fn from_stream(stream: UnixStream) -> UnixKeyOperations {
+        UnixKeyOperations {
+            stream: Mutex::new(stream),
+        }
+    }
+
+    fn call(&self, request: &[u8]) -> Result<Vec<u8>, ()> {
+        let mut stream = self.stream.lock().map_err(|_| ())?;
+        write_frame(&mut *stream, request).map_err(|_| ())?;
+        let response = read_frame(&mut *stream).map_err(|_| ())?.ok_or(())?;
+
+        match response.split_first() {
+            Some((&STATUS_OK, body)) => Ok(body.to_vec()),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl KeyOperations for UnixKeyOperations {
+    fn sign(&self, ticket: &Ticket, metadata: PrivateMetadata) -> Result<BlindSignature, ()> {
+        let mut request = Vec::with_capacity(1 + 32 + 1);
+        request.push(OP_SIGN);
+        request.extend_from_slice(&ticket.to_bytes());
+        request.push(match metadata {
+            PrivateMetadata::Bit(b) => b as u8,
+            PrivateMetadata::Poison => 2,
+        });
+
+        BlindSignature::from_bytes(&self.call(&request)?)
+    }
+
+    fn verify_mac(
+        &self,
+        t: &Scalar,
+        p_big: &RistrettoPoint,
+        q_big: &RistrettoPoint,
+    ) -> Result<MacVerdict, ()> {
+        let mut request = Vec::with_capacity(1 + 32 * 3);
+        request.push(OP_MAC);
+        request.extend_from_slice(&scalar_bytes(t));
+        request.extend_from_slice(&ristretto_bytes(p_big));
+        request.extend_from_slice(&ristretto_bytes(q_big));
+
+        match self.call(&request)?.as_slice() {
+            [0] => Ok(MacVerdict::Bit(false)),
+            [1] => Ok(MacVerdict::Bit(true)),
+            [2] => Ok(MacVerdict::Poison),
+            [3] => Ok(MacVerdict::Invalid),
+            _ => Err(()),
+        }
+    }
+}
+
+// Signer side: answers one request body
+pub fn handle_signer_request<K: KeyOperations>(ops: &K, request: &[u8]) -> Vec<u8> {
+    let mut response = vec![STATUS_OK];
+    match signer_request(ops, request) {
+        Ok(body) => response.extend_from_slice(&body),
+        Err(()) => response[0] = STATUS_ERROR,
+    }
+    response
+}
+
+fn signer_request<K: KeyOperations>(ops: &K, request: &[u8]) -> Result<Vec<u8>, ()> {
+    let mut reader = ByteReader::new(request);
+    let op = reader.read_array::<1>()?[0];
+    match op {
+        OP_SIGN => {
+            let ticket = Ticket::from_bytes(reader.read(32)?)?;
+            let metadata = match reader.read_array::<1>()?[0] {
+                0 => PrivateMetadata::Bit(false),
+                1 => PrivateMetadata::Bit(true),
+                2 => PrivateMetadata::Poison,
+                _ => return Err(()),
+            };
+            reader.finish()?;
+            Ok(ops.sign(&ticket, metadata)?.to_bytes())
+        }
+        OP_MAC => {
+            let t = scalar_from_bytes(reader.read(32)?)?;
+            let p_big = ristretto_from_bytes(reader.read(32)?)?;
+            let q_big = ristretto_from_bytes(reader.read(32)?)?;
+            reader.finish()?;
+            let verdict = match ops.verify_mac(&t, &p_big, &q_big)? {
+                MacVerdict::Bit(b) => b as u8,
+                MacVerdict::Poison => 2,
+                MacVerdict::Invalid => 3,
+            };
+            Ok(vec![verdict])
+        }
+        _ => Err(()),
+    }
+}
+
+// Serves requests on one connection until the client closes it
+pub fn serve_signer_connection<S, K>(stream: &mut S, ops: &K) -> io::Result<()>
+where
+    S: Read + Write,
+    K: KeyOperations,
+{
+    while let Some(request) = read_frame(stream)? {
+        write_frame(stream, &handle_signer_request(ops, &request))?;
+    }
+    Ok(())
+}
+
+// Signer daemon loop: serves connections one after another
+#[cfg(unix)]
+pub fn serve_signer<K: KeyOperations>(listener: &UnixListener, ops: &K) -> io::Result<()> {
+    for stream in listener.incoming() {
+        // A misbehaving client only loses its own connection
+        let _ = serve_signer_connection(&mut stream?, ops);
+    }
+    Ok(())
+}
+
+fn write_frame<W: Write>(writer: &mut W, body: &[u8]) -> io::Result<()> {
+    writer.write_all(&(body.len() as u32).to_be_bytes())?;
+    writer.write_all(body)?;
+    writer.flush()
+}
+
+// None on a clean end of stream before a frame
+fn read_frame<R: Read>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "signer frame too long",
+        ));
+    }
+    let mut body = vec![0u8; len];
+    reader.read_exact(&mut body)?;
+    Ok(Some(body))
+}
@@ -0,0 +1,37 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use std::collections::HashSet;
+
+use curve25519_dalek_ng::scalar::Scalar;
+
+use crate::utils::scalar_bytes;
+
+// The redeemed t values; t = tc + ts is unique per issued token
+#[derive(Debug, Default)]
+pub struct SpentTokens {
+    spent: HashSet<[u8; 32]>,
+}
+
+impl SpentTokens {
+    pub fn new() -> SpentTokens {
+        SpentTokens::default()
+    }
+
+    pub fn is_spent(&self, t: &Scalar) -> bool {
+        self.spent.contains(&scalar_bytes(t))
+    }
+
+    // Returns false if the token was already spent
+    pub fn mark_spent(&mut self, t: &Scalar) -> bool {
+        self.spent.insert(scalar_bytes(t))
+    }
+
+    pub fn len(&self) -> usize {
+        self.spent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spent.is_empty()
+    }
+}
@@ -0,0 +1,283 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+// Redemption split across two servers. With h = y / 2 and x' = x + h, a valid
+// token satisfies Q - (x' + t * z) * P = (2b - 1) * h * P, i.e. R = +h * P for
+// b = 1 and R = -h * P for b = 0. The front-end holds (x', z) and computes R;
+// without h it cannot tell the sign. It forwards (beta * P, beta * Q, beta * R)
+// for a fresh beta and the nullifier N = (n * t) * H, and proves that R was
+// computed from that Q under its key for the same t that N is formed from.
+// t itself never reaches the back-end, and without n, N cannot be matched
+// against the t values the front-end sees. The back-end, holding only h,
+// answers each nullifier once: it recovers the bit and returns +-h * P' in a
+// fixed order, so the front-end can check validity itself without learning
+// which candidate matched.
+
+use std::collections::HashSet;
+
+use curve25519_dalek_ng::{ristretto::RistrettoPoint, scalar::Scalar, traits::Identity};
+use rand_core::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+
+use crate::{
+    keys::SecretKey,
+    nonce::HedgedNonces,
+    params::PUBLIC_PARAMS,
+    token::Token,
+    utils::{non_zero_scalar, ristretto_bytes, scalar_bytes},
+};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct FrontEndKey {
+    pub x_shifted: Scalar,
+    pub z: Scalar,
+    // Nullifier key
+    pub n: Scalar,
+}
+
+// h, and the front-end's public key (x' * G, z * G, n * G) to check its proofs
+// against
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct BackEndKey {
+    pub h: Scalar,
+    pub x_shifted_big: RistrettoPoint,
+    pub z_big: RistrettoPoint,
+    pub n_big: RistrettoPoint,
+}
+
+// What the front-end forwards to the back-end
+pub struct BlindedToken {
+    pub p_big: RistrettoPoint,
+    pub q_big: RistrettoPoint,
+    pub r_big: RistrettoPoint,
+    pub nullifier: RistrettoPoint,
+    pub pi: SplitProof,
+}
+
+// Knowledge of (w, t, u) with
+//   w * G - t * Z = X'
+//   w * P' = Q' - R'
+//   u * G - t * N_key = 0
+//   u * H = N
+// i.e. w = x' + t * z and u = n * t for one t.
+pub struct SplitProof {
+    pub e: Scalar,
+    pub a_w: Scalar,
+    pub a_t: Scalar,
+    pub a_u: Scalar,
+}
+
+// h * P' and -h * P', ordered by encoding
+pub struct Candidates {
+    pub first: RistrettoPoint,
+    pub second: RistrettoPoint,
+}
+
+// The nullifiers the back-end has answered for
+#[derive(Debug, Default)]
+pub struct AnsweredTokens {
+    answered: HashSet<[u8; 32]>,
+}
+
+pub fn split_key<R>(rng: &mut R, sk: &SecretKey) -> (FrontEndKey, BackEndKey)
+where
+    R: RngCore + CryptoRng,
+{
+    let h = sk.y * Scalar::from(2u64).invert();
+    let x_shifted = sk.x + h;
+    let n = non_zero_scalar(rng);
+
+    let front_end = FrontEndKey {
+        x_shifted,
+        z: sk.z,
+        n,
+    };
+    let back_end = BackEndKey {
+        h,
+        x_shifted_big: &x_shifted * &PUBLIC_PARAMS.g_big,
+        z_big: &sk.z * &PUBLIC_PARAMS.g_big,
+        n_big: &n * &PUBLIC_PARAMS.g_big,
+    };
+
+    (front_end, back_end)
+}
+
+impl FrontEndKey {
+    pub fn blind<R>(&self, rng: &mut R, token: &Token) -> Result<BlindedToken, ()>
+    where
+        R: RngCore + CryptoRng,
+    {
+        if token.p_big == RistrettoPoint::identity() {
+            return Err(());
+        }
+
+        let mut nonces = HedgedNonces::with_secrets(
+            rng,
+            b"MacTok-SplitRedemption",
+            &[&self.x_shifted, &self.z, &self.n],
+            &[
+                &scalar_bytes(&token.t),
+                &ristretto_bytes(&token.p_big),
+                &ristretto_bytes(&token.q_big),
+            ],
+        );
+
+        let beta = nonces.non_zero_scalar();
+        let p_big = beta * token.p_big;
+        let q_big = beta * token.q_big;
+
+        // R' = Q' - (x' + t * z) * P'
+        let w = self.x_shifted + token.t * self.z;
+        let r_big = q_big - w * p_big;
+
+        let u = self.n * token.t;
+        let nullifier = &u * &PUBLIC_PARAMS.h_big;
+
+        let k_w = nonces.scalar();
+        let k_t = nonces.scalar();
+        let k_u = nonces.scalar();
+        let commitments = [
+            &k_w * &PUBLIC_PARAMS.g_big - k_t * (&self.z * &PUBLIC_PARAMS.g_big),
+            k_w * p_big,
+            &k_u * &PUBLIC_PARAMS.g_big - k_t * (&self.n * &PUBLIC_PARAMS.g_big),
+            &k_u * &PUBLIC_PARAMS.h_big,
+        ];
+
+        let mut blinded = BlindedToken {
+            p_big,
+            q_big,
+            r_big,
+            nullifier,
+            pi: SplitProof {
+                e: Scalar::zero(),
+                a_w: Scalar::zero(),
+                a_t: Scalar::zero(),
+                a_u: Scalar::zero(),
+            },
+        };
+        let e = challenge(&self.public_key(), &blinded, &commitments);
+        blinded.pi = SplitProof {
+            e,
+            a_w: k_w + e * w,
+            a_t: k_t + e * token.t,
+            a_u: k_u + e * u,
+        };
+
+        Ok(blinded)
+    }
+
+    fn public_key(&self) -> [RistrettoPoint; 3] {
+        [
+            &self.x_shifted * &PUBLIC_PARAMS.g_big,
+            &self.z * &PUBLIC_PARAMS.g_big,
+            &self.n * &PUBLIC_PARAMS.g_big,
+        ]
+    }
+}
+
+impl BackEndKey {
+    // answered keeps the nullifier of every token the back-end has answered
+    // for, so that each token is looked at once
+    pub fn recover_bit(
+        &self,
+        blinded: &BlindedToken,
+        answered: &mut AnsweredTokens,
+    ) -> Result<(bool, Candidates), ()> {
+        if blinded.p_big == RistrettoPoint::identity()
+            || blinded.nullifier == RistrettoPoint::identity()
+        {
+            return Err(());
+        }
+        self.verify(blinded)?;
+
+        let h_p_big = self.h * blinded.p_big;
+        let is_true = blinded.r_big == h_p_big;
+        let is_false = blinded.r_big == -h_p_big;
+
+        if !(is_true ^ is_false) {
+            return Err(());
+        }
+        if !answered.mark_answered(&blinded.nullifier) {
+            return Err(());
+        }
+
+        let (first, second) = if ristretto_bytes(&h_p_big) < ristretto_bytes(&-h_p_big) {
+            (h_p_big, -h_p_big)
+        } else {
+            (-h_p_big, h_p_big)
+        };
+
+        Ok((is_true, Candidates { first, second }))
+    }
+
+    fn verify(&self, blinded: &BlindedToken) -> Result<(), ()> {
+        let pi = &blinded.pi;
+
+        // C_1 <-- a_w * G - a_t * Z - e * X'
+        // C_2 <-- a_w * P' - e * (Q' - R')
+        // C_3 <-- a_u * G - a_t * N_key
+        // C_4 <-- a_u * H - e * N
+        let commitments = [
+            &pi.a_w * &PUBLIC_PARAMS.g_big - pi.a_t * self.z_big - pi.e * self.x_shifted_big,
+            pi.a_w * blinded.p_big - pi.e * (blinded.q_big - blinded.r_big),
+            &pi.a_u * &PUBLIC_PARAMS.g_big - pi.a_t * self.n_big,
+            &pi.a_u * &PUBLIC_PARAMS.h_big - pi.e * blinded.nullifier,
+        ];
+
+        let public_key = [self.x_shifted_big, self.z_big, self.n_big];
+        if challenge(&public_key, blinded, &commitments) != pi.e {
+            return Err(());
+        }
+
+        Ok(())
+    }
+}
+
+impl Candidates {
+    pub fn contains(&self, r_big: &RistrettoPoint) -> bool {
+        r_big == &self.first || r_big == &self.second
+    }
+}
+
+impl AnsweredTokens {
+    pub fn new() -> AnsweredTokens {
+        AnsweredTokens::default()
+    }
+
+    // Returns false if the nullifier was already answered for
+    pub fn mark_answered(&mut self, nullifier: &RistrettoPoint) -> bool {
+        self.answered.insert(ristretto_bytes(nullifier))
+    }
+
+    pub fn len(&self) -> usize {
+        self.answered.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.answered.is_empty()
+    }
+}
+
+fn challenge(
+    public_key: &[RistrettoPoint; 3],
+    blinded: &BlindedToken,
+    commitments: &[RistrettoPoint; 4],
+) -> Scalar {
+    let mut hasher = Sha512::new();
+    hasher.update(b"MacTok-SplitRedemption");
+    for point in public_key
+        .iter()
+        .chain([
+            &blinded.p_big,
+            &blinded.q_big,
+            &blinded.r_big,
+            &blinded.nullifier,
+        ])
+        .chain(commitments.iter())
+    {
+        hasher.update(ristretto_bytes(point));
+    }
+
+    Scalar::from_hash(hasher)
+}
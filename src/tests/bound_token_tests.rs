@@ -0,0 +1,45 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use rand_core::OsRng;
+
+use crate::{
+    blind_sig::BlindSignature,
+    bound_token::BoundToken,
+    keys::{PublicKey, SecretKey},
+    server::redeem_bound_token,
+    ticket::Ticket,
+    token::Token,
+};
+
+#[test]
+pub fn bound_token_redemption_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+
+    for b in [true, false] {
+        let (ticket, receipt) = Ticket::create(&mut rng, &pk);
+        let bs = BlindSignature::create(&mut rng, &pk, &sk, &ticket, b);
+        let token = Token::create(&mut rng, &pk, &bs, &ticket, &receipt).unwrap();
+
+        let bound = BoundToken::create(&token, b"GET /resource");
+        assert_eq!(redeem_bound_token(&bound, b"GET /resource", &sk), Ok(b));
+    }
+}
+
+#[test]
+pub fn bound_token_other_message_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+    let (ticket, receipt) = Ticket::create(&mut rng, &pk);
+    let bs = BlindSignature::create(&mut rng, &pk, &sk, &ticket, true);
+    let token = Token::create(&mut rng, &pk, &bs, &ticket, &receipt).unwrap();
+
+    let bound = BoundToken::create(&token, b"GET /resource");
+    assert!(redeem_bound_token(&bound, b"POST /transfer", &sk).is_err());
+
+    let sk2 = SecretKey::create(&mut rng);
+    assert!(redeem_bound_token(&bound, b"GET /resource", &sk2).is_err());
+}
@@ -0,0 +1,98 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use rand_core::OsRng;
+
+use crate::{
+    blind_sig::BlindSignature,
+    challenge::{ChallengeRegistry, ChallengeToken},
+    keys::{PublicKey, SecretKey},
+    server::redeem_challenge_token,
+    ticket::Ticket,
+    token::Token,
+};
+
+fn issue_token(pk: &PublicKey, sk: &SecretKey, b: bool) -> Token {
+    let mut rng = OsRng;
+    let (ticket, receipt) = Ticket::create(&mut rng, pk);
+    let bs = BlindSignature::create(&mut rng, pk, sk, &ticket, b);
+    Token::create(&mut rng, pk, &bs, &ticket, &receipt).unwrap()
+}
+
+#[test]
+pub fn challenge_token_redemption_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+    let mut origin = ChallengeRegistry::new("origin.example", 60);
+
+    let challenge = origin.issue(&mut rng, pk.key_id(), true, 0);
+    let token = ChallengeToken::create(&issue_token(&pk, &sk, true), &challenge);
+    assert_eq!(
+        redeem_challenge_token(&token, &mut origin, &sk, 0),
+        Ok(true)
+    );
+
+    // A nonce challenge is single use
+    let token = ChallengeToken::create(&issue_token(&pk, &sk, true), &challenge);
+    assert!(redeem_challenge_token(&token, &mut origin, &sk, 0).is_err());
+
+    // A challenge without a nonce stays valid, but each token only counts once
+    let challenge = origin.issue(&mut rng, pk.key_id(), false, 0);
+    for b in [false, true] {
+        let token = ChallengeToken::create(&issue_token(&pk, &sk, b), &challenge);
+        assert_eq!(redeem_challenge_token(&token, &mut origin, &sk, 0), Ok(b));
+        assert!(redeem_challenge_token(&token, &mut origin, &sk, 0).is_err());
+    }
+}
+
+#[test]
+pub fn challenge_expiry_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+    let mut origin = ChallengeRegistry::new("origin.example", 60);
+
+    let challenge = origin.issue(&mut rng, pk.key_id(), false, 0);
+    let token = ChallengeToken::create(&issue_token(&pk, &sk, true), &challenge);
+    assert!(redeem_challenge_token(&token, &mut origin, &sk, 60).is_err());
+    assert_eq!(
+        redeem_challenge_token(&token, &mut origin, &sk, 59),
+        Ok(true)
+    );
+
+    // Expired challenges are dropped when the next one is issued
+    origin.issue(&mut rng, pk.key_id(), true, 30);
+    assert_eq!(origin.len(), 2);
+    origin.issue(&mut rng, pk.key_id(), true, 60);
+    assert_eq!(origin.len(), 2);
+    origin.prune(1000);
+    assert!(origin.is_empty());
+}
+
+#[test]
+pub fn challenge_token_mismatch_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+    let mut origin = ChallengeRegistry::new("origin.example", 60);
+    let mut other_origin = ChallengeRegistry::new("other.example", 60);
+
+    // Challenge from another origin
+    let challenge = other_origin.issue(&mut rng, pk.key_id(), true, 0);
+    let token = ChallengeToken::create(&issue_token(&pk, &sk, true), &challenge);
+    assert!(redeem_challenge_token(&token, &mut origin, &sk, 0).is_err());
+
+    // Challenge for another issuer key
+    let sk2 = SecretKey::create(&mut rng);
+    let challenge = origin.issue(&mut rng, PublicKey::create(&sk2).key_id(), true, 0);
+    let token = ChallengeToken::create(&issue_token(&pk, &sk, true), &challenge);
+    assert!(redeem_challenge_token(&token, &mut origin, &sk, 0).is_err());
+
+    // Token bound to a different challenge than the one it claims
+    let challenge = origin.issue(&mut rng, pk.key_id(), true, 0);
+    let other = origin.issue(&mut rng, pk.key_id(), true, 0);
+    let mut token = ChallengeToken::create(&issue_token(&pk, &sk, true), &challenge);
+    token.challenge_digest = other.digest();
+    assert!(redeem_challenge_token(&token, &mut origin, &sk, 0).is_err());
+}
@@ -0,0 +1,101 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use curve25519_dalek_ng::{ristretto::RistrettoPoint, scalar::Scalar, traits::Identity};
+use rand_core::OsRng;
+
+use crate::{
+    blind_sig::BlindSignature,
+    client_binding::{BindingPublicKey, BindingSecretKey, ClientBoundToken, ClientKey},
+    keys::{PublicKey, SecretKey},
+    server::{redeem_client_bound_token, redeem_token, RedemptionOutcome},
+    ticket::Ticket,
+    token::Token,
+};
+
+#[test]
+pub fn client_bound_token_redemption_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+    let bsk = BindingSecretKey::create(&mut rng);
+    let bpk = BindingPublicKey::create(&bsk);
+    let client_key = ClientKey::create(&mut rng);
+
+    for b in [true, false] {
+        let (ticket, receipt) = Ticket::create_bound(&mut rng, &pk, &bpk, &client_key);
+        let bs = BlindSignature::create(&mut rng, &pk, &sk, &ticket, b);
+        let token = Token::create(&mut rng, &pk, &bs, &ticket, &receipt).unwrap();
+
+        let presented = ClientBoundToken::create(&mut rng, &token, &client_key, b"request");
+        assert_eq!(
+            redeem_client_bound_token(&presented, b"request", &sk, &bsk),
+            Ok(b)
+        );
+        assert!(redeem_client_bound_token(&presented, b"other request", &sk, &bsk).is_err());
+    }
+}
+
+#[test]
+pub fn stolen_client_bound_token_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+    let bsk = BindingSecretKey::create(&mut rng);
+    let bpk = BindingPublicKey::create(&bsk);
+    let client_key = ClientKey::create(&mut rng);
+    let (ticket, receipt) = Ticket::create_bound(&mut rng, &pk, &bpk, &client_key);
+    let bs = BlindSignature::create(&mut rng, &pk, &sk, &ticket, true);
+    let token = Token::create(&mut rng, &pk, &bs, &ticket, &receipt).unwrap();
+
+    // Without the client key the token is worthless
+    assert_eq!(redeem_token(&token, &sk), RedemptionOutcome::Invalid);
+    let thief_key = ClientKey::create(&mut rng);
+    let presented = ClientBoundToken::create(&mut rng, &token, &thief_key, b"request");
+    assert!(redeem_client_bound_token(&presented, b"request", &sk, &bsk).is_err());
+
+    // Swapping in another K_P without knowing its discrete log fails the proof
+    let mut presented = ClientBoundToken::create(&mut rng, &token, &thief_key, b"request");
+    let honest = ClientBoundToken::create(&mut rng, &token, &client_key, b"request");
+    presented.k_big_p = honest.k_big_p;
+    assert!(redeem_client_bound_token(&presented, b"request", &sk, &bsk).is_err());
+
+    // An unbound token cannot pass as bound to the zero key
+    let (ticket, receipt) = Ticket::create(&mut rng, &pk);
+    let bs = BlindSignature::create(&mut rng, &pk, &sk, &ticket, true);
+    let token = Token::create(&mut rng, &pk, &bs, &ticket, &receipt).unwrap();
+    let zero_key = ClientKey {
+        k: Scalar::zero(),
+        k_big: RistrettoPoint::identity(),
+    };
+    let presented = ClientBoundToken::create(&mut rng, &token, &zero_key, b"request");
+    assert!(presented.verify_possession(b"request").is_ok());
+    assert!(redeem_client_bound_token(&presented, b"request", &sk, &bsk).is_err());
+}
+
+#[test]
+pub fn client_bound_token_unlinkability_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+    let bsk = BindingSecretKey::create(&mut rng);
+    let bpk = BindingPublicKey::create(&bsk);
+    let client_key = ClientKey::create(&mut rng);
+
+    // Nothing the client sends repeats between two of its redemptions
+    let mut presentations = Vec::new();
+    for _ in 0..2 {
+        let (ticket, receipt) = Ticket::create_bound(&mut rng, &pk, &bpk, &client_key);
+        let bs = BlindSignature::create(&mut rng, &pk, &sk, &ticket, true);
+        let token = Token::create(&mut rng, &pk, &bs, &ticket, &receipt).unwrap();
+        let presented = ClientBoundToken::create(&mut rng, &token, &client_key, b"request");
+        assert_eq!(
+            redeem_client_bound_token(&presented, b"request", &sk, &bsk),
+            Ok(true)
+        );
+        assert_ne!(presented.k_big_p, client_key.k_big);
+        presentations.push(presented);
+    }
+    assert_ne!(presentations[0].k_big_p, presentations[1].k_big_p);
+    assert_ne!(presentations[0].p_big, presentations[1].p_big);
+}
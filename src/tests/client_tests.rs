@@ -0,0 +1,59 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use rand_core::OsRng;
+
+use crate::{
+    blind_sig::BlindSignature,
+    challenge::ChallengeRegistry,
+    client::PendingIssuance,
+    keys::{PublicKey, SecretKey},
+    server::{redeem_bound_token, redeem_challenge_token, redeem_token, RedemptionOutcome},
+};
+
+#[test]
+pub fn client_flow_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+
+    let pending = PendingIssuance::create(&mut rng, &pk);
+    let bs = BlindSignature::create(&mut rng, &pk, &sk, pending.ticket(), true);
+    let token = pending.finalize(&mut rng, &pk, &bs).unwrap();
+    assert_eq!(
+        redeem_token(&token.present(), &sk),
+        RedemptionOutcome::Valid(true)
+    );
+
+    let pending = PendingIssuance::create(&mut rng, &pk);
+    let bs = BlindSignature::create(&mut rng, &pk, &sk, pending.ticket(), false);
+    let token = pending.finalize(&mut rng, &pk, &bs).unwrap();
+    let bound = token.present_bound(b"GET /resource");
+    assert_eq!(redeem_bound_token(&bound, b"GET /resource", &sk), Ok(false));
+
+    let mut origin = ChallengeRegistry::new("origin.example", 60);
+    let challenge = origin.issue(&mut rng, pk.key_id(), true, 0);
+    let pending = PendingIssuance::create(&mut rng, &pk);
+    let bs = BlindSignature::create(&mut rng, &pk, &sk, pending.ticket(), true);
+    let token = pending.finalize(&mut rng, &pk, &bs).unwrap();
+    let presented = token.present_to_challenge(&challenge);
+    assert_eq!(
+        redeem_challenge_token(&presented, &mut origin, &sk, 0),
+        Ok(true)
+    );
+}
+
+#[test]
+pub fn client_finalize_fail_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+
+    // A signature over a different ticket is rejected and the pending
+    // issuance is gone
+    let pending = PendingIssuance::create(&mut rng, &pk);
+    let other = PendingIssuance::create(&mut rng, &pk);
+    let bs = BlindSignature::create(&mut rng, &pk, &sk, other.ticket(), true);
+    assert!(pending.finalize(&mut rng, &pk, &bs).is_err());
+    assert!(other.finalize(&mut rng, &pk, &bs).is_ok());
+}
@@ -0,0 +1,108 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use curve25519_dalek_ng::scalar::Scalar;
+use rand_core::OsRng;
+
+use crate::credential::{
+    attribute_from_bytes, Attribute, Credential, CredentialPresentation, CredentialPublicKey,
+    CredentialRequest, CredentialResponse, CredentialSecretKey, Predicate,
+};
+
+fn issue_credential(
+    csk: &CredentialSecretKey,
+    cpk: &CredentialPublicKey,
+    attributes: &[Attribute],
+) -> Credential {
+    let mut rng = OsRng;
+    let (request, receipt) = CredentialRequest::create(&mut rng, cpk, attributes).unwrap();
+    let response = CredentialResponse::create(&mut rng, csk, cpk, &request).unwrap();
+    Credential::create(cpk, &request, &receipt, &response).unwrap()
+}
+
+#[test]
+pub fn credential_selective_disclosure_test() {
+    let mut rng = OsRng;
+    let csk = CredentialSecretKey::create(&mut rng, 3);
+    let cpk = CredentialPublicKey::create(&csk);
+
+    let tier = attribute_from_bytes(b"gold");
+    let country = attribute_from_bytes(b"NL");
+    let age_bucket = Scalar::from(3u64);
+    let credential = issue_credential(
+        &csk,
+        &cpk,
+        &[
+            Attribute::Revealed(tier),
+            Attribute::Hidden(country),
+            Attribute::Hidden(age_bucket),
+        ],
+    );
+
+    // Reveal only the tier and prove the age bucket is one of the adult ones
+    let predicates = [Predicate::OneOf {
+        index: 2,
+        values: vec![Scalar::from(2u64), Scalar::from(3u64), Scalar::from(4u64)],
+    }];
+    let presentation =
+        CredentialPresentation::create(&mut rng, &cpk, &credential, &[0], &predicates, b"ctx")
+            .unwrap();
+    assert_eq!(presentation.revealed, vec![Some(tier), None, None]);
+    assert!(presentation.verify(&csk, &cpk, b"ctx").is_ok());
+    assert!(presentation.verify(&csk, &cpk, b"other").is_err());
+
+    // Presentations of the same credential are unlinkable
+    let again =
+        CredentialPresentation::create(&mut rng, &cpk, &credential, &[0], &predicates, b"ctx")
+            .unwrap();
+    assert_ne!(presentation.u_big, again.u_big);
+    assert!(again.verify(&csk, &cpk, b"ctx").is_ok());
+
+    // A predicate the attribute does not satisfy cannot be proven
+    let adult_only = [Predicate::OneOf {
+        index: 2,
+        values: vec![Scalar::from(4u64)],
+    }];
+    assert!(
+        CredentialPresentation::create(&mut rng, &cpk, &credential, &[0], &adult_only, b"ctx")
+            .is_err()
+    );
+}
+
+#[test]
+pub fn credential_forgery_test() {
+    let mut rng = OsRng;
+    let csk = CredentialSecretKey::create(&mut rng, 2);
+    let cpk = CredentialPublicKey::create(&csk);
+    let credential = issue_credential(
+        &csk,
+        &cpk,
+        &[
+            Attribute::Revealed(Scalar::from(1u64)),
+            Attribute::Hidden(Scalar::from(7u64)),
+        ],
+    );
+
+    // Changing a revealed attribute invalidates the presentation
+    let mut presentation =
+        CredentialPresentation::create(&mut rng, &cpk, &credential, &[0], &[], b"").unwrap();
+    presentation.revealed[0] = Some(Scalar::from(2u64));
+    assert!(presentation.verify(&csk, &cpk, b"").is_err());
+
+    // A credential claiming different attributes does not verify
+    let forged = Credential {
+        u_big: credential.u_big,
+        w_big: credential.w_big,
+        attributes: vec![Scalar::from(2u64), Scalar::from(7u64)],
+    };
+    let presentation =
+        CredentialPresentation::create(&mut rng, &cpk, &forged, &[0, 1], &[], b"").unwrap();
+    assert!(presentation.verify(&csk, &cpk, b"").is_err());
+
+    // A different issuer key rejects the presentation
+    let other_csk = CredentialSecretKey::create(&mut rng, 2);
+    let presentation =
+        CredentialPresentation::create(&mut rng, &cpk, &credential, &[], &[], b"").unwrap();
+    assert!(presentation.verify(&csk, &cpk, b"").is_ok());
+    assert!(presentation.verify(&other_csk, &cpk, b"").is_err());
+}
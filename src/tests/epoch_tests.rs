@@ -0,0 +1,94 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use rand_core::OsRng;
+
+use crate::{
+    blind_sig::BlindSignature,
+    epoch::{Epoch, EpochPublicKey, EpochSecretKey, EpochSpentTokens, EpochToken},
+    server::redeem_epoch_token,
+    ticket::Ticket,
+};
+
+fn issue_epoch_token(esk: &EpochSecretKey, epk: &EpochPublicKey, b: bool) -> EpochToken {
+    let mut rng = OsRng;
+    let (ticket, receipt) = Ticket::create(&mut rng, &epk.pk);
+    let bs = BlindSignature::create(&mut rng, &epk.pk, &esk.sk, &ticket, b);
+    EpochToken::create(&mut rng, epk, &bs, &ticket, &receipt).unwrap()
+}
+
+#[test]
+pub fn epoch_token_redemption_test() {
+    let mut rng = OsRng;
+    let epoch = Epoch {
+        id: 7,
+        not_before: 1000,
+        not_after: 2000,
+    };
+    let esk = EpochSecretKey::create(&mut rng, epoch);
+    let epk = EpochPublicKey::create(&esk);
+    let mut spent = EpochSpentTokens::new();
+
+    let token = issue_epoch_token(&esk, &epk, true);
+    assert_eq!(token.epoch, 7);
+    assert_eq!(redeem_epoch_token(&token, &esk, &mut spent, 1500), Ok(true));
+
+    // Double spend within the epoch
+    assert!(redeem_epoch_token(&token, &esk, &mut spent, 1501).is_err());
+
+    let token = issue_epoch_token(&esk, &epk, false);
+    assert_eq!(redeem_epoch_token(&token, &esk, &mut spent, 1999), Ok(false));
+}
+
+#[test]
+pub fn epoch_token_expiry_test() {
+    let mut rng = OsRng;
+    let epoch = Epoch {
+        id: 1,
+        not_before: 1000,
+        not_after: 2000,
+    };
+    let esk = EpochSecretKey::create(&mut rng, epoch);
+    let epk = EpochPublicKey::create(&esk);
+    let mut spent = EpochSpentTokens::new();
+
+    let token = issue_epoch_token(&esk, &epk, true);
+    assert!(redeem_epoch_token(&token, &esk, &mut spent, 2000).is_err());
+    assert!(redeem_epoch_token(&token, &esk, &mut spent, 999).is_err());
+
+    // A token carrying another epoch id is rejected
+    let other = Epoch { id: 2, ..epoch };
+    let other_esk = EpochSecretKey::create(&mut rng, other);
+    assert!(redeem_epoch_token(&token, &other_esk, &mut spent, 1500).is_err());
+}
+
+#[test]
+pub fn epoch_spent_tokens_prune_test() {
+    let mut rng = OsRng;
+    let first = Epoch {
+        id: 1,
+        not_before: 0,
+        not_after: 100,
+    };
+    let second = Epoch {
+        id: 2,
+        not_before: 100,
+        not_after: 200,
+    };
+    let first_esk = EpochSecretKey::create(&mut rng, first);
+    let first_epk = EpochPublicKey::create(&first_esk);
+    let second_esk = EpochSecretKey::create(&mut rng, second);
+    let second_epk = EpochPublicKey::create(&second_esk);
+    let mut spent = EpochSpentTokens::new();
+
+    let first_token = issue_epoch_token(&first_esk, &first_epk, true);
+    let second_token = issue_epoch_token(&second_esk, &second_epk, true);
+    assert!(redeem_epoch_token(&first_token, &first_esk, &mut spent, 50).is_ok());
+    assert!(redeem_epoch_token(&second_token, &second_esk, &mut spent, 150).is_ok());
+    assert_eq!(spent.epoch_count(), 2);
+
+    spent.prune(150);
+    assert_eq!(spent.epoch_count(), 1);
+    assert!(!spent.is_spent(&first, &first_token.token.t));
+    assert!(spent.is_spent(&second, &second_token.token.t));
+}
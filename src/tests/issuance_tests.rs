@@ -0,0 +1,178 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use rand_core::OsRng;
+
+use crate::{
+    blind_sig::PrivateMetadata,
+    issuance::{IssuanceRequest, IssuanceResponse},
+    keys::{PublicKey, SecretKey},
+    policy::{IssuanceContext, IssuanceDecision},
+    server::{handle_issuance_request, redeem_token, RedemptionOutcome},
+};
+
+// Issues true to attested clients, poisons flagged ones and false otherwise
+fn policy(context: &IssuanceContext) -> IssuanceDecision {
+    if context.headers.get("x-flagged").is_some() {
+        return IssuanceDecision::Issue(PrivateMetadata::Poison);
+    }
+    IssuanceDecision::Issue(PrivateMetadata::Bit(context.attestation == Some(true)))
+}
+
+fn refuse(_: &IssuanceContext) -> IssuanceDecision {
+    IssuanceDecision::Refuse
+}
+
+fn verify_proof(proof: &[u8]) -> bool {
+    proof == b"attestation"
+}
+
+#[test]
+pub fn issuance_round_trip_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+
+    let (request, batch) =
+        IssuanceRequest::create(&mut rng, &pk, 4, Some(b"attestation".to_vec())).unwrap();
+    let request_bytes = request.to_bytes();
+    assert_eq!(IssuanceRequest::from_bytes(&request_bytes), Ok(request));
+
+    let response = handle_issuance_request(
+        &mut rng,
+        &request_bytes,
+        IssuanceContext::default(),
+        &pk,
+        &sk,
+        &policy,
+        verify_proof,
+    )
+    .unwrap();
+
+    let tokens = batch.finalize(&mut rng, &pk, &response).unwrap();
+    assert_eq!(tokens.len(), 4);
+    for token in tokens {
+        assert_eq!(
+            redeem_token(&token.present(), &sk),
+            RedemptionOutcome::Valid(true)
+        );
+    }
+
+    // Without a proof, or with one that does not verify
+    for proof in [None, Some(b"forged".to_vec())] {
+        let (request, batch) = IssuanceRequest::create(&mut rng, &pk, 1, proof).unwrap();
+        let response = handle_issuance_request(
+            &mut rng,
+            &request.to_bytes(),
+            IssuanceContext::default(),
+            &pk,
+            &sk,
+            &policy,
+            verify_proof,
+        )
+        .unwrap();
+        let token = batch.finalize(&mut rng, &pk, &response).unwrap().remove(0);
+        assert_eq!(
+            redeem_token(&token.present(), &sk),
+            RedemptionOutcome::Valid(false)
+        );
+    }
+
+    // The policy sees the headers, under lowercase names, and may poison
+    let (request, batch) =
+        IssuanceRequest::create(&mut rng, &pk, 2, Some(b"attestation".to_vec())).unwrap();
+    let response = handle_issuance_request(
+        &mut rng,
+        &request.to_bytes(),
+        IssuanceContext::from_headers(&[("X-Flagged", "1")]),
+        &pk,
+        &sk,
+        &policy,
+        verify_proof,
+    )
+    .unwrap();
+    for token in batch.finalize(&mut rng, &pk, &response).unwrap() {
+        assert_eq!(
+            redeem_token(&token.present(), &sk),
+            RedemptionOutcome::Poisoned
+        );
+    }
+}
+
+#[test]
+pub fn issuance_mismatch_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+    let other_sk = SecretKey::create(&mut rng);
+    let other_pk = PublicKey::create(&other_sk);
+
+    // The issuer rejects requests for another key, bad versions and its policy's refusals
+    let (request, _) = IssuanceRequest::create(&mut rng, &other_pk, 1, None).unwrap();
+    assert!(handle_issuance_request(
+        &mut rng,
+        &request.to_bytes(),
+        IssuanceContext::default(),
+        &pk,
+        &sk,
+        &policy,
+        verify_proof
+    )
+    .is_err());
+    let (request, _) = IssuanceRequest::create(&mut rng, &pk, 1, None).unwrap();
+    let mut bytes = request.to_bytes();
+    assert!(handle_issuance_request(
+        &mut rng,
+        &bytes,
+        IssuanceContext::default(),
+        &pk,
+        &sk,
+        &refuse,
+        verify_proof
+    )
+    .is_err());
+    bytes[0] = 2;
+    assert!(handle_issuance_request(
+        &mut rng,
+        &bytes,
+        IssuanceContext::default(),
+        &pk,
+        &sk,
+        &policy,
+        verify_proof
+    )
+    .is_err());
+
+    // The client rejects a response to a different request
+    let (request, batch) = IssuanceRequest::create(&mut rng, &pk, 2, None).unwrap();
+    let (other_request, _) = IssuanceRequest::create(&mut rng, &pk, 2, None).unwrap();
+    let response = handle_issuance_request(
+        &mut rng,
+        &other_request.to_bytes(),
+        IssuanceContext::default(),
+        &pk,
+        &sk,
+        &policy,
+        verify_proof,
+    )
+    .unwrap();
+    assert!(batch.finalize(&mut rng, &pk, &response).is_err());
+
+    // Or one with the right nonce but signatures over other tickets
+    let (request_2, batch) = IssuanceRequest::create(&mut rng, &pk, 2, None).unwrap();
+    let mut response = IssuanceResponse::from_bytes(
+        &handle_issuance_request(
+            &mut rng,
+            &request.to_bytes(),
+            IssuanceContext::default(),
+            &pk,
+            &sk,
+            &policy,
+            verify_proof,
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    response.nonce = request_2.nonce;
+    assert!(batch.finalize(&mut rng, &pk, &response.to_bytes()).is_err());
+}
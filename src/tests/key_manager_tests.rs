@@ -0,0 +1,106 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use rand_core::OsRng;
+
+use crate::{
+    blind_sig::BlindSignature,
+    epoch::{EpochPublicKey, EpochSpentTokens, EpochToken},
+    key_manager::{KeyManager, KeyState},
+    server::{redeem_managed_token, redeem_managed_token_report, RedemptionOutcome},
+    ticket::Ticket,
+};
+
+const WEEK: u64 = 7 * 24 * 60 * 60;
+const DAY: u64 = 24 * 60 * 60;
+
+#[test]
+pub fn key_rotation_states_test() {
+    let mut rng = OsRng;
+    let mut keys = KeyManager::new(WEEK, DAY);
+    keys.rotate(&mut rng, 0);
+
+    let first = keys.issuing_key(0).unwrap().epoch.id;
+    assert_eq!(keys.keys().len(), 2);
+    let second = keys.keys()[1].key.epoch.id;
+    assert_eq!(keys.state(second, 0), Some(KeyState::Pending));
+    assert_eq!(keys.public_keys(0).len(), 2);
+
+    keys.rotate(&mut rng, WEEK);
+    assert_eq!(keys.state(first, WEEK), Some(KeyState::RedeemOnly));
+    assert_eq!(keys.state(second, WEEK), Some(KeyState::Active));
+    assert_eq!(keys.issuing_key(WEEK).unwrap().epoch.id, second);
+    assert_eq!(keys.public_keys(WEEK).len(), 3);
+
+    keys.rotate(&mut rng, WEEK + DAY);
+    assert_eq!(keys.state(first, WEEK + DAY), None);
+    assert!(keys.redemption_key(first, WEEK + DAY).is_none());
+}
+
+#[test]
+pub fn key_rotation_grace_redemption_test() {
+    let mut rng = OsRng;
+    let mut keys = KeyManager::new(WEEK, DAY);
+    let mut spent = EpochSpentTokens::new();
+    keys.rotate(&mut rng, 0);
+
+    let esk = keys.issuing_key(10).unwrap();
+    let epk = keys
+        .public_keys(10)
+        .into_iter()
+        .find(|epk| epk.epoch.id == esk.epoch.id)
+        .unwrap();
+    let mut tokens = Vec::new();
+    for _ in 0..2 {
+        let (ticket, receipt) = Ticket::create(&mut rng, &epk.pk);
+        let bs = BlindSignature::create(&mut rng, &epk.pk, &esk.sk, &ticket, true);
+        tokens.push(EpochToken::create(&mut rng, &epk, &bs, &ticket, &receipt).unwrap());
+    }
+
+    // Still redeemable in the grace period after the next key took over issuance
+    keys.rotate(&mut rng, WEEK + 10);
+    assert_ne!(keys.issuing_key(WEEK + 10).unwrap().epoch.id, epk.epoch.id);
+    assert_eq!(
+        redeem_managed_token(&tokens[0], &keys, &mut spent, WEEK + 10),
+        Ok(true)
+    );
+
+    // Rejected once the key retires
+    keys.rotate(&mut rng, WEEK + DAY);
+    assert!(redeem_managed_token(&tokens[1], &keys, &mut spent, WEEK + DAY).is_err());
+}
+
+#[test]
+pub fn managed_redemption_report_test() {
+    let mut rng = OsRng;
+    let mut keys = KeyManager::new(WEEK, DAY);
+    let mut spent = EpochSpentTokens::new();
+    keys.rotate(&mut rng, 0);
+
+    let esk = keys.issuing_key(0).unwrap();
+    let epk = EpochPublicKey::create(esk);
+    let key_id = epk.pk.key_id();
+    let mut tokens = Vec::new();
+    for _ in 0..2 {
+        let (ticket, receipt) = Ticket::create(&mut rng, &epk.pk);
+        let bs = BlindSignature::create(&mut rng, &epk.pk, &esk.sk, &ticket, true);
+        tokens.push(EpochToken::create(&mut rng, &epk, &bs, &ticket, &receipt).unwrap());
+    }
+
+    let report = redeem_managed_token_report(&tokens[0], &keys, &mut spent, 10);
+    assert_eq!(report.key_id, Some(key_id));
+    assert_eq!(report.epoch, Some(epk.epoch.id));
+    assert_eq!(report.outcome, RedemptionOutcome::Valid(true));
+    let report = redeem_managed_token_report(&tokens[0], &keys, &mut spent, 10);
+    assert_eq!(report.outcome, RedemptionOutcome::DoubleSpent);
+
+    // Past the grace period, before and after the key is dropped
+    let report = redeem_managed_token_report(&tokens[1], &keys, &mut spent, WEEK + DAY);
+    assert_eq!(report.outcome, RedemptionOutcome::ExpiredEpoch);
+    assert_eq!(report.key_id, Some(key_id));
+    keys.rotate(&mut rng, WEEK + DAY);
+    let report = redeem_managed_token_report(&tokens[1], &keys, &mut spent, WEEK + DAY);
+    assert_eq!(report.outcome, RedemptionOutcome::UnknownKey);
+    assert_eq!(report.key_id, None);
+    assert_eq!(report.epoch, Some(epk.epoch.id));
+}
@@ -1,6 +1,27 @@
 // Copyright (c) Microsoft Corporation. All rights reserved.
 // Licensed under the MIT license.
 
+mod bound_token_tests;
+mod challenge_tests;
+mod client_binding_tests;
+mod client_tests;
+mod credential_tests;
+mod epoch_tests;
+mod issuance_tests;
+mod key_manager_tests;
 mod keys_tests;
+mod nonce_tests;
 mod params_tests;
+mod policy_tests;
+mod presentation_tests;
+mod privacy_pass_tests;
+mod rate_limit_tests;
 mod redemption_tests;
+mod scheme_tests;
+#[cfg(feature = "async")]
+mod service_tests;
+#[cfg(unix)]
+mod signer_tests;
+mod split_redemption_tests;
+mod threshold_tests;
+mod voprf_tests;
@@ -0,0 +1,196 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use curve25519_dalek_ng::scalar::Scalar;
+use rand_core::{CryptoRng, OsRng, RngCore};
+
+use crate::{
+    blind_sig::BlindSignature,
+    credential::{
+        Attribute, Credential, CredentialPublicKey, CredentialRequest, CredentialResponse,
+        CredentialSecretKey,
+    },
+    keys::{PublicKey, SecretKey},
+    nonce::HedgedNonces,
+    server::{redeem_token, RedemptionOutcome},
+    threshold::{deal_shares, IssuanceNonce, IssuedNonces, PartialBlindSignature, PublicKeyShare},
+    ticket::Ticket,
+    token::Token,
+    voprf::{VoprfEvaluation, VoprfPublicKey, VoprfSecretKey, VoprfTicket, VoprfToken},
+};
+
+// An RNG that is stuck on the same output, as after a bad fork or VM snapshot
+struct StuckRng;
+
+impl RngCore for StuckRng {
+    fn next_u32(&mut self) -> u32 {
+        0x42424242
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        0x4242424242424242
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        dest.fill(0x42);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for StuckRng {}
+
+#[test]
+pub fn stuck_rng_issuance_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+    let (ticket_1, receipt_1) = Ticket::create(&mut rng, &pk);
+    let (ticket_2, receipt_2) = Ticket::create(&mut rng, &pk);
+
+    let bs_1 = BlindSignature::create(&mut StuckRng, &pk, &sk, &ticket_1, true);
+    let bs_2 = BlindSignature::create(&mut StuckRng, &pk, &sk, &ticket_2, true);
+
+    // Different requests never share d, ts or any proof nonce
+    assert_ne!(bs_1.u_big, bs_2.u_big);
+    assert_ne!(bs_1.ts, bs_2.ts);
+    assert_ne!(bs_1.pi.c_big, bs_2.pi.c_big);
+    assert_ne!(bs_1.pi.a_d, bs_2.pi.a_d);
+    assert_ne!(bs_1.pi.a_rho, bs_2.pi.a_rho);
+    assert_ne!(bs_1.pi.a_w, bs_2.pi.a_w);
+    assert_ne!(bs_1.pi.e_zero, bs_2.pi.e_zero);
+
+    // Signatures from a stuck RNG are still valid
+    let token_1 = Token::create(&mut rng, &pk, &bs_1, &ticket_1, &receipt_1).unwrap();
+    let token_2 = Token::create(&mut rng, &pk, &bs_2, &ticket_2, &receipt_2).unwrap();
+    assert_eq!(redeem_token(&token_1, &sk), RedemptionOutcome::Valid(true));
+    assert_eq!(redeem_token(&token_2, &sk), RedemptionOutcome::Valid(true));
+
+    // The same request and bit with a stuck RNG only reproduces the same signature
+    let bs_3 = BlindSignature::create(&mut StuckRng, &pk, &sk, &ticket_1, true);
+    assert_eq!(bs_1.to_bytes(), bs_3.to_bytes());
+    let bs_4 = BlindSignature::create(&mut StuckRng, &pk, &sk, &ticket_1, false);
+    assert_ne!(bs_1.u_big, bs_4.u_big);
+}
+
+#[test]
+pub fn hedged_nonces_test() {
+    let sk = SecretKey::create(&mut OsRng);
+    let other_sk = SecretKey::create(&mut OsRng);
+
+    let mut nonces = HedgedNonces::new(&mut StuckRng, b"test", &sk, &[], &[b"transcript"]);
+    let first = nonces.scalar();
+    assert_ne!(first, nonces.scalar());
+
+    // Each of the secret key, the transcript and the randomness changes the nonces
+    let mut same = HedgedNonces::new(&mut StuckRng, b"test", &sk, &[], &[b"transcript"]);
+    assert_eq!(first, same.scalar());
+    let mut other = HedgedNonces::new(&mut StuckRng, b"test", &other_sk, &[], &[b"transcript"]);
+    assert_ne!(first, other.scalar());
+    let mut other = HedgedNonces::new(&mut StuckRng, b"test", &sk, &[], &[b"transcrip", b"t"]);
+    assert_ne!(first, other.scalar());
+    let mut other = HedgedNonces::new(&mut OsRng, b"test", &sk, &[], &[b"transcript"]);
+    assert_ne!(first, other.scalar());
+}
+
+#[test]
+pub fn stuck_rng_threshold_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+    let shares = deal_shares(&mut rng, &sk, 2, 3);
+    let pk_shares: Vec<PublicKeyShare> = shares.iter().map(PublicKeyShare::create).collect();
+    let (ticket_1, _) = Ticket::create(&mut rng, &pk);
+    let (ticket_2, _) = Ticket::create(&mut rng, &pk);
+
+    let mut issued = IssuedNonces::new();
+    let mut issued_other = IssuedNonces::new();
+
+    // Round 1 nonces and commitment proofs differ per ticket
+    let (nonce_1, commitment_1) =
+        IssuanceNonce::create(&mut StuckRng, &shares[0], &[1, 2], &ticket_1, &mut issued).unwrap();
+    let (_, commitment_2) =
+        IssuanceNonce::create(&mut StuckRng, &shares[0], &[1, 2], &ticket_2, &mut issued).unwrap();
+    assert_ne!(commitment_1.u_big, commitment_2.u_big);
+    assert_ne!(commitment_1.ts, commitment_2.ts);
+    assert_ne!(commitment_1.sig.a, commitment_2.sig.a);
+    assert!(commitment_1.verify(&pk_shares).is_ok());
+
+    // and per set of co-signers for the same ticket
+    let (_, commitment_3) =
+        IssuanceNonce::create(&mut StuckRng, &shares[0], &[1, 3], &ticket_1, &mut issued).unwrap();
+    assert_ne!(commitment_1.u_big, commitment_3.u_big);
+    assert_ne!(commitment_1.ts, commitment_3.ts);
+    assert_ne!(commitment_1.sig.a, commitment_3.sig.a);
+    assert!(commitment_3.verify(&pk_shares).is_ok());
+
+    // The same session is not started twice, in whatever order the signers come
+    assert!(
+        IssuanceNonce::create(&mut StuckRng, &shares[0], &[2, 1], &ticket_1, &mut issued).is_err()
+    );
+    assert_eq!(issued.len(), 3);
+
+    // Servers with the same stuck RNG still use different proof nonces
+    let (nonce_other, commitment_other) = IssuanceNonce::create(
+        &mut StuckRng,
+        &shares[1],
+        &[1, 2],
+        &ticket_1,
+        &mut issued_other,
+    )
+    .unwrap();
+    let commitments = [commitment_1, commitment_other];
+    let partial = PartialBlindSignature::create(
+        &mut StuckRng,
+        &shares[0],
+        &pk_shares,
+        &pk,
+        nonce_1,
+        &commitments,
+        &ticket_1,
+        true,
+    )
+    .unwrap();
+    let partial_other = PartialBlindSignature::create(
+        &mut StuckRng,
+        &shares[1],
+        &pk_shares,
+        &pk,
+        nonce_other,
+        &commitments,
+        &ticket_1,
+        false,
+    )
+    .unwrap();
+    assert_ne!(partial.pi.a_w, partial_other.pi.a_w);
+    assert_ne!(partial.pi.e_zero, partial_other.pi.e_zero);
+}
+
+#[test]
+pub fn stuck_rng_credential_and_voprf_test() {
+    let mut rng = OsRng;
+    let csk = CredentialSecretKey::create(&mut rng, 1);
+    let cpk = CredentialPublicKey::create(&csk);
+    let attributes = [Attribute::Hidden(Scalar::from(1u64))];
+    let (request_1, receipt_1) = CredentialRequest::create(&mut rng, &cpk, &attributes).unwrap();
+    let (request_2, _) = CredentialRequest::create(&mut rng, &cpk, &attributes).unwrap();
+
+    let response_1 = CredentialResponse::create(&mut StuckRng, &csk, &cpk, &request_1).unwrap();
+    let response_2 = CredentialResponse::create(&mut StuckRng, &csk, &cpk, &request_2).unwrap();
+    assert_ne!(response_1.u_big, response_2.u_big);
+    assert_ne!(response_1.pi.a_delta, response_2.pi.a_delta);
+    assert!(Credential::create(&cpk, &request_1, &receipt_1, &response_1).is_ok());
+
+    let sk = VoprfSecretKey::create(&mut rng);
+    let pk = VoprfPublicKey::create(&sk);
+    let (ticket_1, receipt_1) = VoprfTicket::create(&mut rng).unwrap();
+    let (ticket_2, _) = VoprfTicket::create(&mut rng).unwrap();
+
+    let evaluation_1 = VoprfEvaluation::create(&mut StuckRng, &sk, &pk, &ticket_1);
+    let evaluation_2 = VoprfEvaluation::create(&mut StuckRng, &sk, &pk, &ticket_2);
+    assert_ne!(evaluation_1.pi.s, evaluation_2.pi.s);
+    assert!(VoprfToken::create(&pk, &ticket_1, &receipt_1, &evaluation_1).is_ok());
+}
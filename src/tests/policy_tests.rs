@@ -0,0 +1,156 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use rand_core::OsRng;
+use serde_json::Value;
+
+use crate::{
+    blind_sig::PrivateMetadata,
+    issuance::IssuanceRequest,
+    keys::{PublicKey, SecretKey},
+    policy::{replay, IssuanceContext, IssuanceDecision, IssuancePolicy, RulePolicy},
+    server::{handle_issuance_request, issue_with_policy, redeem_token, RedemptionOutcome},
+    ticket::Ticket,
+    token::Token,
+};
+
+const RULES: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/src/tests/fixtures/policy/rules.json"
+);
+const RECORDING: &str = include_str!("fixtures/policy/requests.jsonl");
+// Like RECORDING, but with the proof the request carries next to the context
+// the caller fills in
+const HANDLER_RECORDING: &str = include_str!("fixtures/policy/handler_requests.jsonl");
+
+#[test]
+pub fn rule_policy_test() {
+    let policy = RulePolicy::from_file(RULES).unwrap();
+    assert_eq!(policy.rules.len(), 5);
+
+    let mut context = IssuanceContext {
+        attestation: Some(true),
+        risk_score: Some(0.1),
+        ..Default::default()
+    };
+    assert_eq!(
+        policy.decide(&context),
+        IssuanceDecision::Issue(PrivateMetadata::Bit(true))
+    );
+    context
+        .headers
+        .insert("x-client".to_string(), "crawler".to_string());
+    assert_eq!(
+        policy.decide(&context),
+        IssuanceDecision::Issue(PrivateMetadata::Bit(false))
+    );
+    context.attestation = None;
+    context.risk_score = Some(0.5);
+    assert_eq!(policy.decide(&context), IssuanceDecision::Refuse);
+
+    // Unknown conditions and decisions are configuration errors
+    assert!(RulePolicy::from_json(r#"{"rules": [], "default": "maybe"}"#).is_err());
+    assert!(RulePolicy::from_json(
+        r#"{"rules": [{"when": {"country": "XX"}, "decision": "true"}], "default": "true"}"#
+    )
+    .is_err());
+    assert!(RulePolicy::from_file("/nonexistent/rules.json").is_err());
+}
+
+#[test]
+pub fn policy_replay_test() {
+    let policy = RulePolicy::from_file(RULES).unwrap();
+    let results = replay(&policy, RECORDING).unwrap();
+    assert_eq!(results.len(), 9);
+    assert!(results.iter().all(|result| result.matches()));
+    assert_eq!(results[8].line, 10);
+    assert_eq!(results[8].expected, None);
+
+    // A changed rule set shows up as mismatches
+    let strict = RulePolicy::from_json(
+        r#"{"rules": [{"when": {}, "decision": "refuse"}], "default": "true"}"#,
+    )
+    .unwrap();
+    let results = replay(&strict, RECORDING).unwrap();
+    let mismatches: Vec<usize> = results
+        .iter()
+        .filter(|result| !result.matches())
+        .map(|result| result.line)
+        .collect();
+    assert_eq!(mismatches, vec![1, 4, 5, 6, 7, 9]);
+    assert!(replay(&policy, "{\"context\": {\"attestation\": 1}}").is_err());
+}
+
+#[test]
+pub fn policy_issuance_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+    let policy = RulePolicy::from_file(RULES).unwrap();
+
+    let cases = [
+        (Some(true), 0.1, Some(RedemptionOutcome::Valid(true))),
+        (Some(true), 0.5, Some(RedemptionOutcome::Valid(false))),
+        (Some(true), 0.95, Some(RedemptionOutcome::Poisoned)),
+        (None, 0.7, None),
+    ];
+    for (attestation, risk_score, outcome) in cases {
+        let context = IssuanceContext {
+            attestation,
+            risk_score: Some(risk_score),
+            ..Default::default()
+        };
+        let (ticket, receipt) = Ticket::create(&mut rng, &pk);
+        let bs = issue_with_policy(&mut rng, &ticket, &pk, &sk, &policy, &context);
+        match outcome {
+            Some(outcome) => {
+                let token = Token::create(&mut rng, &pk, &bs.unwrap(), &ticket, &receipt).unwrap();
+                assert_eq!(redeem_token(&token, &sk), outcome);
+            }
+            None => assert!(bs.is_err()),
+        }
+    }
+}
+
+#[test]
+pub fn policy_handler_replay_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+    let policy = RulePolicy::from_file(RULES).unwrap();
+
+    for line in HANDLER_RECORDING.lines() {
+        let record: Value = serde_json::from_str(line).unwrap();
+        let proof = record["proof"]
+            .as_str()
+            .map(|proof| proof.as_bytes().to_vec());
+        let context = IssuanceContext::from_json_value(&record["context"]).unwrap();
+
+        // A caller-filled attestation is replaced by the proof check
+        let (request, batch) = IssuanceRequest::create(&mut rng, &pk, 1, proof).unwrap();
+        let response = handle_issuance_request(
+            &mut rng,
+            &request.to_bytes(),
+            context,
+            &pk,
+            &sk,
+            &policy,
+            |proof: &[u8]| proof == b"attestation",
+        );
+
+        let expected = match record["expected"].as_str().unwrap() {
+            "true" => RedemptionOutcome::Valid(true),
+            "false" => RedemptionOutcome::Valid(false),
+            "poison" => RedemptionOutcome::Poisoned,
+            _ => {
+                assert!(response.is_err(), "{}", line);
+                continue;
+            }
+        };
+        let token = batch
+            .finalize(&mut rng, &pk, &response.unwrap())
+            .unwrap()
+            .remove(0);
+        assert_eq!(redeem_token(&token.present(), &sk), expected, "{}", line);
+    }
+}
@@ -0,0 +1,55 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use rand_core::OsRng;
+
+use crate::{
+    blind_sig::BlindSignature,
+    keys::{PublicKey, SecretKey},
+    presentation::TokenPresentation,
+    server::redeem_presentation,
+    ticket::Ticket,
+    token::Token,
+};
+
+#[test]
+pub fn multi_show_presentation_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+
+    for b in [true, false] {
+        let (ticket, receipt) = Ticket::create(&mut rng, &pk);
+        let bs = BlindSignature::create(&mut rng, &pk, &sk, &ticket, b);
+        let token = Token::create(&mut rng, &pk, &bs, &ticket, &receipt).unwrap();
+
+        // The same token can be shown repeatedly, each show looking fresh
+        let first = TokenPresentation::create(&mut rng, &pk, &token, b"context");
+        let second = TokenPresentation::create(&mut rng, &pk, &token, b"context");
+        assert_ne!(first.p_big, second.p_big);
+        assert_ne!(first.c_big_t, second.c_big_t);
+        assert_eq!(redeem_presentation(&first, b"context", &sk), Ok(b));
+        assert_eq!(redeem_presentation(&second, b"context", &sk), Ok(b));
+        assert!(redeem_presentation(&first, b"other context", &sk).is_err());
+    }
+}
+
+#[test]
+pub fn forged_presentation_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+    let (ticket, receipt) = Ticket::create(&mut rng, &pk);
+    let bs = BlindSignature::create(&mut rng, &pk, &sk, &ticket, true);
+    let mut token = Token::create(&mut rng, &pk, &bs, &ticket, &receipt).unwrap();
+
+    // A different key does not accept the presentation
+    let other_sk = SecretKey::create(&mut rng);
+    let presentation = TokenPresentation::create(&mut rng, &pk, &token, b"");
+    assert!(redeem_presentation(&presentation, b"", &other_sk).is_err());
+
+    // Neither does a token with a tampered MAC
+    token.q_big += token.p_big;
+    let presentation = TokenPresentation::create(&mut rng, &pk, &token, b"");
+    assert!(redeem_presentation(&presentation, b"", &sk).is_err());
+}
@@ -0,0 +1,92 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use rand_core::OsRng;
+
+use crate::{
+    blind_sig::BlindSignature,
+    keys::{PublicKey, SecretKey},
+    privacy_pass::{
+        PrivacyPassChallenge, PrivacyPassToken, PrivateTokenChallenge, TokenRequest, TokenResponse,
+        TOKEN_TYPE,
+    },
+    server::redeem_privacy_pass_token,
+    ticket::Ticket,
+    token::Token,
+};
+
+#[test]
+pub fn privacy_pass_issuance_and_redemption_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+
+    // Origin sends a challenge
+    let challenge = PrivacyPassChallenge::new("issuer.example", &[7u8; 32], &["origin.example"]);
+    let header = PrivateTokenChallenge {
+        challenge: challenge.clone(),
+        token_key: PublicKey::create(&sk),
+        max_age: Some(60),
+    }
+    .to_header_value()
+    .unwrap();
+    let received = PrivateTokenChallenge::from_header_value(&header).unwrap();
+    assert_eq!(received.challenge, challenge);
+    assert_eq!(received.token_key, pk);
+    assert_eq!(received.max_age, Some(60));
+
+    for b in [true, false] {
+        // Client to issuer and back, over the wire
+        let (ticket, receipt) = Ticket::create(&mut rng, &received.token_key);
+        let request_bytes = TokenRequest::create(ticket, &received.token_key).to_bytes();
+
+        let request = TokenRequest::from_bytes(&request_bytes).unwrap();
+        assert!(request.matches(&pk));
+        let bs = BlindSignature::create(&mut rng, &pk, &sk, &request.blinded_msg, b);
+        let response_bytes = TokenResponse { blind_sig: bs }.to_bytes();
+
+        let response = TokenResponse::from_bytes(&response_bytes).unwrap();
+        let token = Token::create(
+            &mut rng,
+            &received.token_key,
+            &response.blind_sig,
+            &request.blinded_msg,
+            &receipt,
+        )
+        .unwrap();
+
+        // Client to origin
+        let pp_token =
+            PrivacyPassToken::create(&token, &received.challenge, &received.token_key).unwrap();
+        let authorization = pp_token.to_header_value();
+        let presented = PrivacyPassToken::from_header_value(&authorization).unwrap();
+        assert_eq!(presented, pp_token);
+        assert_eq!(presented.token_type, TOKEN_TYPE);
+        assert_eq!(
+            redeem_privacy_pass_token(&presented, &challenge, &sk),
+            Ok(b)
+        );
+
+        // The token is bound to its challenge
+        let other = PrivacyPassChallenge::new("issuer.example", &[8u8; 32], &["origin.example"]);
+        assert!(redeem_privacy_pass_token(&presented, &other, &sk).is_err());
+    }
+}
+
+#[test]
+pub fn privacy_pass_parsing_test() {
+    let challenge = PrivacyPassChallenge::new("issuer.example", &[], &["a.example", "b.example"]);
+    let bytes = challenge.to_bytes().unwrap();
+    assert_eq!(PrivacyPassChallenge::from_bytes(&bytes).unwrap(), challenge);
+
+    // Trailing bytes, bad redemption contexts and other schemes are rejected
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert!(PrivacyPassChallenge::from_bytes(&trailing).is_err());
+    assert!(PrivacyPassChallenge::new("issuer.example", &[1u8; 5], &[])
+        .to_bytes()
+        .is_err());
+    assert!(PrivacyPassToken::from_header_value("Bearer token=\"abc\"").is_err());
+    assert!(PrivacyPassToken::from_header_value("PrivateToken token=\"abc\"").is_err());
+    assert!(TokenRequest::from_bytes(&[0u8; 34]).is_err());
+}
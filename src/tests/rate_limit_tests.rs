@@ -0,0 +1,197 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use rand_core::OsRng;
+
+use crate::{
+    client_binding::{BindingPublicKey, BindingSecretKey, ClientKey, TicketBindingProof},
+    keys::{PublicKey, SecretKey},
+    rate_limit::{IssuanceLimiter, RateLimitedToken, RateLimiter},
+    server::{redeem_rate_limited_token, sign_bound_ticket},
+    spent_tokens::SpentTokens,
+    ticket::Ticket,
+    token::Token,
+};
+
+fn issue_bound_token(
+    pk: &PublicKey,
+    sk: &SecretKey,
+    bpk: &BindingPublicKey,
+    client_key: &ClientKey,
+    issuer: &mut IssuanceLimiter,
+) -> Result<Token, ()> {
+    let mut rng = OsRng;
+    let (ticket, receipt) = Ticket::create_bound(&mut rng, pk, bpk, client_key);
+    let pi = TicketBindingProof::create(&mut rng, pk, bpk, client_key, &ticket, &receipt);
+    let bs = sign_bound_ticket(
+        &mut rng,
+        &ticket,
+        &pi,
+        &client_key.k_big,
+        pk,
+        sk,
+        bpk,
+        issuer,
+        0,
+        true,
+    )?;
+    Token::create(&mut rng, pk, &bs, &ticket, &receipt)
+}
+
+fn registered_issuer(client_key: &ClientKey) -> IssuanceLimiter {
+    let mut issuer = IssuanceLimiter::new(86400, 10);
+    issuer.register(&client_key.k_big);
+    issuer
+}
+
+#[test]
+pub fn rate_limited_redemption_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+    let bsk = BindingSecretKey::create(&mut rng);
+    let bpk = BindingPublicKey::create(&bsk);
+    let client_key = ClientKey::create(&mut rng);
+    let mut issuer = registered_issuer(&client_key);
+    let mut limiter = RateLimiter::new("example.com", 3600, 2);
+    let mut spent = SpentTokens::new();
+    let now = 7200;
+    let window = limiter.window_at(now);
+
+    let mut pseudonyms = Vec::new();
+    for expected in 1..=2 {
+        let token = issue_bound_token(&pk, &sk, &bpk, &client_key, &mut issuer).unwrap();
+        let presented =
+            RateLimitedToken::create(&mut rng, &token, &client_key, "example.com", window, b"req");
+        let redemption =
+            redeem_rate_limited_token(&presented, b"req", &sk, &bsk, &mut limiter, &mut spent, now)
+                .unwrap();
+        assert!(redemption.b);
+        assert_eq!(redemption.count, expected);
+        pseudonyms.push(redemption.pseudonym);
+    }
+    assert_eq!(pseudonyms[0], pseudonyms[1]);
+
+    // The third token in the window is over the limit and is not spent
+    let token = issue_bound_token(&pk, &sk, &bpk, &client_key, &mut issuer).unwrap();
+    let presented =
+        RateLimitedToken::create(&mut rng, &token, &client_key, "example.com", window, b"req");
+    assert!(redeem_rate_limited_token(
+        &presented,
+        b"req",
+        &sk,
+        &bsk,
+        &mut limiter,
+        &mut spent,
+        now
+    )
+    .is_err());
+    assert!(!spent.is_spent(&token.t));
+
+    // In the next window the same token is accepted under a fresh pseudonym
+    let later = now + 3600;
+    let presented = RateLimitedToken::create(
+        &mut rng,
+        &token,
+        &client_key,
+        "example.com",
+        limiter.window_at(later),
+        b"req",
+    );
+    let redemption = redeem_rate_limited_token(
+        &presented,
+        b"req",
+        &sk,
+        &bsk,
+        &mut limiter,
+        &mut spent,
+        later,
+    )
+    .unwrap();
+    assert_eq!(redemption.count, 1);
+    assert_ne!(redemption.pseudonym, pseudonyms[0]);
+}
+
+#[test]
+pub fn rate_limited_pseudonym_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+    let bsk = BindingSecretKey::create(&mut rng);
+    let bpk = BindingPublicKey::create(&bsk);
+    let client_key = ClientKey::create(&mut rng);
+    let mut issuer = registered_issuer(&client_key);
+    let mut limiter = RateLimiter::new("example.com", 3600, 1);
+    let mut spent = SpentTokens::new();
+    let window = limiter.window_at(0);
+    let token = issue_bound_token(&pk, &sk, &bpk, &client_key, &mut issuer).unwrap();
+
+    // A proof made for another origin is rejected
+    let presented =
+        RateLimitedToken::create(&mut rng, &token, &client_key, "other.com", window, b"req");
+    assert!(
+        redeem_rate_limited_token(&presented, b"req", &sk, &bsk, &mut limiter, &mut spent, 0)
+            .is_err()
+    );
+
+    // So is a fresh pseudonym from a client key the ticket was not bound to
+    let other_key = ClientKey::create(&mut rng);
+    let presented =
+        RateLimitedToken::create(&mut rng, &token, &other_key, "example.com", window, b"req");
+    assert!(
+        redeem_rate_limited_token(&presented, b"req", &sk, &bsk, &mut limiter, &mut spent, 0)
+            .is_err()
+    );
+    assert!(spent.is_empty());
+}
+
+#[test]
+pub fn rate_limited_fresh_key_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+    let bsk = BindingSecretKey::create(&mut rng);
+    let bpk = BindingPublicKey::create(&bsk);
+    let client_key = ClientKey::create(&mut rng);
+    let mut issuer = registered_issuer(&client_key);
+    let mut limiter = RateLimiter::new("example.com", 3600, 1);
+    let mut spent = SpentTokens::new();
+    let window = limiter.window_at(0);
+
+    let token = issue_bound_token(&pk, &sk, &bpk, &client_key, &mut issuer).unwrap();
+    let presented =
+        RateLimitedToken::create(&mut rng, &token, &client_key, "example.com", window, b"req");
+    assert!(
+        redeem_rate_limited_token(&presented, b"req", &sk, &bsk, &mut limiter, &mut spent, 0)
+            .is_ok()
+    );
+
+    // The same client cannot get a second token under a fresh key, which
+    // would come with a fresh pseudonym
+    let fresh_key = ClientKey::create(&mut rng);
+    assert!(issue_bound_token(&pk, &sk, &bpk, &fresh_key, &mut issuer).is_err());
+
+    // Nor by claiming its registered key for a ticket bound to the fresh one
+    let (ticket, receipt) = Ticket::create_bound(&mut rng, &pk, &bpk, &fresh_key);
+    let pi = TicketBindingProof::create(&mut rng, &pk, &bpk, &fresh_key, &ticket, &receipt);
+    assert!(sign_bound_ticket(
+        &mut rng,
+        &ticket,
+        &pi,
+        &client_key.k_big,
+        &pk,
+        &sk,
+        &bpk,
+        &mut issuer,
+        0,
+        true
+    )
+    .is_err());
+
+    // Issuance itself is limited per registered key
+    let mut issuer = IssuanceLimiter::new(86400, 2);
+    issuer.register(&client_key.k_big);
+    assert!(issue_bound_token(&pk, &sk, &bpk, &client_key, &mut issuer).is_ok());
+    assert!(issue_bound_token(&pk, &sk, &bpk, &client_key, &mut issuer).is_ok());
+    assert!(issue_bound_token(&pk, &sk, &bpk, &client_key, &mut issuer).is_err());
+}
@@ -1,12 +1,17 @@
 // Copyright (c) Microsoft Corporation. All rights reserved.
 // Licensed under the MIT license.
 
+use curve25519_dalek_ng::{ristretto::RistrettoPoint, scalar::Scalar, traits::Identity};
 use rand_core::OsRng;
 
 use crate::{
-    blind_sig::BlindSignature,
+    blind_sig::{BlindSignature, PrivateMetadata},
     keys::{PublicKey, SecretKey},
-    server::redeem_token,
+    server::{
+        exchange_token, redeem_token, redeem_token_report, redeem_tokens, MultiRedemption,
+        RedemptionOutcome, RedemptionReport,
+    },
+    spent_tokens::SpentTokens,
     ticket::Ticket,
     token::Token,
 };
@@ -21,13 +26,13 @@ pub fn token_redemption_test() {
     let token = Token::create(&mut rng, &pk, &bs, &ticket, &receipt);
     let redemption = redeem_token(&token.unwrap(), &sk);
 
-    assert_eq!(redemption.unwrap(), true);
+    assert_eq!(redemption, RedemptionOutcome::Valid(true));
 
     let bs = BlindSignature::create(&mut rng, &pk, &sk, &ticket, false);
     let token = Token::create(&mut rng, &pk, &bs, &ticket, &receipt);
     let redemption = redeem_token(&token.unwrap(), &sk);
 
-    assert_eq!(redemption.unwrap(), false);
+    assert_eq!(redemption, RedemptionOutcome::Valid(false));
 }
 
 #[test]
@@ -42,7 +47,7 @@ pub fn token_redemption_fail_test() {
     let sk2 = SecretKey::create(&mut rng);
     let redemption = redeem_token(&token.unwrap(), &sk2);
 
-    assert!(redemption.is_err());
+    assert_eq!(redemption, RedemptionOutcome::Invalid);
 
     let sk = SecretKey::create(&mut rng);
     let sk2 = SecretKey::create(&mut rng);
@@ -53,3 +58,162 @@ pub fn token_redemption_fail_test() {
 
     assert!(token.is_err());
 }
+
+#[test]
+pub fn token_exchange_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+    let mut spent = SpentTokens::new();
+
+    let (ticket, receipt) = Ticket::create(&mut rng, &pk);
+    let bs = BlindSignature::create(&mut rng, &pk, &sk, &ticket, false);
+    let token = Token::create(&mut rng, &pk, &bs, &ticket, &receipt).unwrap();
+
+    // Flip the bit on exchange
+    let (new_ticket, new_receipt) = Ticket::create(&mut rng, &pk);
+    let new_bs =
+        exchange_token(&mut rng, &token, &new_ticket, &pk, &sk, &mut spent, |b| !b).unwrap();
+    let new_token = Token::create(&mut rng, &pk, &new_bs, &new_ticket, &new_receipt).unwrap();
+    assert_eq!(
+        redeem_token(&new_token, &sk),
+        RedemptionOutcome::Valid(true)
+    );
+    assert!(spent.is_spent(&token.t));
+
+    // The old token cannot be exchanged again
+    let (ticket, _) = Ticket::create(&mut rng, &pk);
+    assert!(exchange_token(&mut rng, &token, &ticket, &pk, &sk, &mut spent, |b| b).is_err());
+
+    // An invalid token is not marked spent and gets nothing
+    let other_sk = SecretKey::create(&mut rng);
+    assert!(exchange_token(
+        &mut rng,
+        &new_token,
+        &ticket,
+        &pk,
+        &other_sk,
+        &mut spent,
+        |b| b
+    )
+    .is_err());
+    assert!(!spent.is_spent(&new_token.t));
+}
+
+#[test]
+pub fn poisoned_token_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+
+    // The client accepts a poisoned signature like any other
+    let (ticket, receipt) = Ticket::create(&mut rng, &pk);
+    let bs =
+        BlindSignature::create_with_metadata(&mut rng, &pk, &sk, &ticket, PrivateMetadata::Poison);
+    let token = Token::create(&mut rng, &pk, &bs, &ticket, &receipt).unwrap();
+
+    assert_eq!(redeem_token(&token, &sk), RedemptionOutcome::Poisoned);
+
+    for b in [true, false] {
+        let bs = BlindSignature::create_with_metadata(
+            &mut rng,
+            &pk,
+            &sk,
+            &ticket,
+            PrivateMetadata::Bit(b),
+        );
+        let token = Token::create(&mut rng, &pk, &bs, &ticket, &receipt).unwrap();
+        assert_eq!(redeem_token(&token, &sk), RedemptionOutcome::Valid(b));
+    }
+
+    // Forgeries and tokens for other keys have an invalid MAC
+    let other_sk = SecretKey::create(&mut rng);
+    assert_eq!(redeem_token(&token, &other_sk), RedemptionOutcome::Invalid);
+}
+
+#[test]
+pub fn multi_token_redemption_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+    let mut spent = SpentTokens::new();
+
+    let mut tokens: Vec<Token> = [true, false, true]
+        .iter()
+        .map(|b| {
+            let (ticket, receipt) = Ticket::create(&mut rng, &pk);
+            let bs = BlindSignature::create(&mut rng, &pk, &sk, &ticket, *b);
+            Token::create(&mut rng, &pk, &bs, &ticket, &receipt).unwrap()
+        })
+        .collect();
+
+    // The wrong count is rejected outright
+    assert!(redeem_tokens(&tokens, 2, &sk, &mut spent).is_err());
+
+    // One bad token spends nothing
+    let other_sk = SecretKey::create(&mut rng);
+    assert!(redeem_tokens(&tokens, 3, &other_sk, &mut spent).is_err());
+    assert!(spent.is_empty());
+
+    assert_eq!(
+        redeem_tokens(&tokens, 3, &sk, &mut spent),
+        Ok(MultiRedemption {
+            all_true: false,
+            bits: vec![true, false, true],
+        })
+    );
+    assert_eq!(spent.len(), 3);
+
+    // Reusing any of them fails
+    tokens.truncate(1);
+    assert!(redeem_tokens(&tokens, 1, &sk, &mut spent).is_err());
+}
+
+#[test]
+pub fn redemption_report_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+    let mut spent = SpentTokens::new();
+    let report = |outcome| RedemptionReport {
+        key_id: Some(pk.key_id()),
+        epoch: None,
+        outcome,
+    };
+
+    let (ticket, receipt) = Ticket::create(&mut rng, &pk);
+    let bs = BlindSignature::create(&mut rng, &pk, &sk, &ticket, false);
+    let token = Token::create(&mut rng, &pk, &bs, &ticket, &receipt).unwrap();
+    assert_eq!(
+        redeem_token_report(&token, &pk, &sk, &mut spent),
+        report(RedemptionOutcome::Valid(false))
+    );
+    assert_eq!(
+        redeem_token_report(&token, &pk, &sk, &mut spent),
+        report(RedemptionOutcome::DoubleSpent)
+    );
+
+    // A forged token is invalid and not recorded as spent
+    let forged = Token {
+        t: token.t + Scalar::one(),
+        p_big: token.p_big,
+        q_big: token.q_big,
+    };
+    assert_eq!(
+        redeem_token_report(&forged, &pk, &sk, &mut spent),
+        report(RedemptionOutcome::Invalid)
+    );
+    assert!(!spent.is_spent(&forged.t));
+
+    // An identity P is malformed, whatever Q is
+    let degenerate = Token {
+        t: forged.t,
+        p_big: RistrettoPoint::identity(),
+        q_big: token.q_big,
+    };
+    assert_eq!(
+        redeem_token_report(&degenerate, &pk, &sk, &mut spent),
+        report(RedemptionOutcome::Malformed)
+    );
+    assert!(!spent.is_spent(&degenerate.t));
+}
@@ -0,0 +1,50 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use rand_core::OsRng;
+
+use crate::{
+    blind_sig::PrivateMetadata,
+    scheme::{PrivateBitScheme, TokenScheme, VoprfScheme},
+    server::RedemptionOutcome,
+};
+
+// Application code that only knows the trait
+fn issue_and_redeem<S: TokenScheme>(metadata: S::Metadata) -> Result<S::Outcome, ()> {
+    let mut rng = OsRng;
+    let (sk, pk) = S::create_keys(&mut rng);
+    let (request, state) = S::request(&mut rng, &pk)?;
+    let response = S::issue(&mut rng, &sk, &pk, &request, metadata);
+    let token = S::finalize(&mut rng, &pk, &request, state, &response)?;
+    S::redeem(&sk, &token)
+}
+
+#[test]
+pub fn private_bit_scheme_test() {
+    assert_eq!(
+        issue_and_redeem::<PrivateBitScheme>(PrivateMetadata::Bit(true)),
+        Ok(RedemptionOutcome::Valid(true))
+    );
+    assert_eq!(
+        issue_and_redeem::<PrivateBitScheme>(PrivateMetadata::Bit(false)),
+        Ok(RedemptionOutcome::Valid(false))
+    );
+    assert_eq!(
+        issue_and_redeem::<PrivateBitScheme>(PrivateMetadata::Poison),
+        Ok(RedemptionOutcome::Poisoned)
+    );
+}
+
+#[test]
+pub fn voprf_scheme_test() {
+    assert_eq!(issue_and_redeem::<VoprfScheme>(()), Ok(()));
+
+    // A token redeemed under another key fails
+    let mut rng = OsRng;
+    let (sk, pk) = VoprfScheme::create_keys(&mut rng);
+    let (other_sk, _) = VoprfScheme::create_keys(&mut rng);
+    let (request, state) = VoprfScheme::request(&mut rng, &pk).unwrap();
+    let response = VoprfScheme::issue(&mut rng, &sk, &pk, &request, ());
+    let token = VoprfScheme::finalize(&mut rng, &pk, &request, state, &response).unwrap();
+    assert!(VoprfScheme::redeem(&other_sk, &token).is_err());
+}
@@ -0,0 +1,165 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use std::{collections::HashMap, sync::Arc, sync::Mutex};
+#[cfg(unix)]
+use std::{os::unix::net::UnixStream, thread};
+
+use rand_core::OsRng;
+use tokio::runtime::{Builder, Runtime};
+
+#[cfg(unix)]
+use crate::signer::{serve_signer_connection, UnixKeyOperations};
+use crate::{
+    blind_sig::PrivateMetadata,
+    issuance::IssuanceRequest,
+    keys::{KeyId, PublicKey, SecretKey},
+    policy::{IssuanceContext, IssuanceDecision},
+    server::{redeem_token, RedemptionOutcome, RedemptionReport},
+    service::{AsyncIssuer, AsyncRedeemer, TokenService},
+    signer::LocalKeyOperations,
+    spent_tokens::SpentTokens,
+};
+
+type TestPolicy = fn(&IssuanceContext) -> IssuanceDecision;
+type TestVerifier = fn(&[u8]) -> bool;
+
+fn verify_proof(proof: &[u8]) -> bool {
+    proof == b"attested"
+}
+
+fn runtime() -> Runtime {
+    Builder::new_current_thread().build().unwrap()
+}
+
+type TestService<O> =
+    TokenService<HashMap<KeyId, Arc<O>>, Mutex<SpentTokens>, TestPolicy, TestVerifier>;
+
+fn service<O>(policy: TestPolicy, pk: &PublicKey, ops: O) -> TestService<O> {
+    let mut keys = HashMap::new();
+    keys.insert(pk.key_id(), Arc::new(ops));
+
+    TokenService {
+        keys,
+        spent: Mutex::new(SpentTokens::new()),
+        policy,
+        verify_proof: verify_proof as TestVerifier,
+    }
+}
+
+fn local_service(policy: TestPolicy) -> (TestService<LocalKeyOperations>, PublicKey) {
+    let ops = LocalKeyOperations::new(SecretKey::create(&mut OsRng));
+    let pk = PublicKey::create(&ops.sk);
+    (service(policy, &pk, ops), pk)
+}
+
+#[test]
+pub fn async_issue_and_redeem_test() {
+    let (service, pk) = local_service(|context| {
+        IssuanceDecision::Issue(PrivateMetadata::Bit(context.attestation == Some(true)))
+    });
+
+    runtime().block_on(async {
+        let (request, batch) =
+            IssuanceRequest::create(&mut OsRng, &pk, 2, Some(b"attested".to_vec())).unwrap();
+        let response = service.issue(&request.to_bytes(), &[]).await.unwrap();
+        let mut tokens = batch.finalize(&mut OsRng, &pk, &response).unwrap();
+
+        let token = tokens.pop().unwrap().present().to_bytes();
+        assert_eq!(
+            service.redeem(&pk.key_id(), &token).await,
+            Ok(RedemptionReport {
+                key_id: Some(pk.key_id()),
+                epoch: None,
+                outcome: RedemptionOutcome::Valid(true),
+            })
+        );
+
+        // A replay of the same message is reported as such
+        let report = service.redeem(&pk.key_id(), &token).await.unwrap();
+        assert_eq!(report.outcome, RedemptionOutcome::DoubleSpent);
+        let token = tokens.pop().unwrap().present().to_bytes();
+        let report = service.redeem(&pk.key_id(), &token).await.unwrap();
+        assert_eq!(report.outcome, RedemptionOutcome::Valid(true));
+
+        // Unknown keys and undecodable tokens
+        let report = service.redeem(&[0u8; 32], &token).await.unwrap();
+        assert_eq!(report.outcome, RedemptionOutcome::UnknownKey);
+        let report = service.redeem(&pk.key_id(), b"garbage").await.unwrap();
+        assert_eq!(report.outcome, RedemptionOutcome::Malformed);
+    });
+}
+
+#[test]
+pub fn async_refusal_test() {
+    let (service, pk) = local_service(|context| match context.headers.get("x-client") {
+        Some(client) if client == "crawler" => IssuanceDecision::Issue(PrivateMetadata::Poison),
+        Some(_) => IssuanceDecision::Refuse,
+        None => IssuanceDecision::Issue(PrivateMetadata::Bit(false)),
+    });
+    let other_sk = SecretKey::create(&mut OsRng);
+    let other_pk = PublicKey::create(&other_sk);
+
+    runtime().block_on(async {
+        // The policy refuses, or poisons
+        let (request, batch) = IssuanceRequest::create(&mut OsRng, &pk, 1, None).unwrap();
+        let request = request.to_bytes();
+        assert!(service
+            .issue(&request, &[("x-client", "browser")])
+            .await
+            .is_err());
+        let response = service
+            .issue(&request, &[("X-Client", "crawler")])
+            .await
+            .unwrap();
+        let token = batch
+            .finalize(&mut OsRng, &pk, &response)
+            .unwrap()
+            .remove(0);
+        assert_eq!(
+            redeem_token(&token.present(), &service.keys[&pk.key_id()].sk),
+            RedemptionOutcome::Poisoned
+        );
+
+        // Unknown keys
+        let (request, _) = IssuanceRequest::create(&mut OsRng, &other_pk, 1, None).unwrap();
+        assert!(service.issue(&request.to_bytes(), &[]).await.is_err());
+        assert!(service.issue(b"garbage", &[]).await.is_err());
+    });
+}
+
+#[cfg(unix)]
+#[test]
+pub fn async_signer_process_test() {
+    let sk = SecretKey::create(&mut OsRng);
+    let pk = PublicKey::create(&sk);
+    let (client, mut server) = UnixStream::pair().unwrap();
+
+    // The service only holds a connection to the signer
+    let signer = thread::spawn(move || {
+        serve_signer_connection(&mut server, &LocalKeyOperations::new(sk)).unwrap();
+    });
+    let service = service(
+        |_| IssuanceDecision::Issue(PrivateMetadata::Bit(true)),
+        &pk,
+        UnixKeyOperations::from_stream(client),
+    );
+
+    runtime().block_on(async {
+        let (request, batch) = IssuanceRequest::create(&mut OsRng, &pk, 1, None).unwrap();
+        let response = service.issue(&request.to_bytes(), &[]).await.unwrap();
+        let token = batch
+            .finalize(&mut OsRng, &pk, &response)
+            .unwrap()
+            .remove(0)
+            .present()
+            .to_bytes();
+        let report = service.redeem(&pk.key_id(), &token).await.unwrap();
+        assert_eq!(report.outcome, RedemptionOutcome::Valid(true));
+        let report = service.redeem(&pk.key_id(), &token).await.unwrap();
+        assert_eq!(report.outcome, RedemptionOutcome::DoubleSpent);
+    });
+
+    drop(service);
+    signer.join().unwrap();
+}
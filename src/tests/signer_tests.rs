@@ -0,0 +1,108 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use std::{os::unix::net::UnixStream, thread};
+
+use curve25519_dalek_ng::{ristretto::RistrettoPoint, traits::Identity};
+use rand_core::OsRng;
+
+use crate::{
+    blind_sig::PrivateMetadata,
+    keys::{PublicKey, SecretKey},
+    params::PUBLIC_PARAMS,
+    server::{redeem_token, redeem_token_with_key_ops, RedemptionOutcome},
+    signer::{
+        handle_signer_request, serve_signer_connection, KeyOperations, LocalKeyOperations,
+        MacVerdict, UnixKeyOperations,
+    },
+    ticket::Ticket,
+    token::Token,
+    utils::ristretto_bytes,
+};
+
+fn issue_and_redeem<K: KeyOperations>(ops: &K, pk: &PublicKey) {
+    let mut rng = OsRng;
+    for b in [false, true] {
+        let (ticket, receipt) = Ticket::create(&mut rng, pk);
+        let bs = ops.sign(&ticket, PrivateMetadata::Bit(b)).unwrap();
+        let token = Token::create(&mut rng, pk, &bs, &ticket, &receipt).unwrap();
+        assert_eq!(
+            redeem_token_with_key_ops(&token, ops),
+            RedemptionOutcome::Valid(b)
+        );
+    }
+
+    let (ticket, receipt) = Ticket::create(&mut rng, pk);
+    let bs = ops.sign(&ticket, PrivateMetadata::Poison).unwrap();
+    let token = Token::create(&mut rng, pk, &bs, &ticket, &receipt).unwrap();
+    assert_eq!(
+        redeem_token_with_key_ops(&token, ops),
+        RedemptionOutcome::Poisoned
+    );
+    assert_eq!(
+        ops.verify_mac(&token.t, &token.p_big, &token.q_big),
+        Ok(MacVerdict::Poison)
+    );
+
+    // A forged Q is invalid, and P = identity is refused outright
+    let q_big = RistrettoPoint::random(&mut rng);
+    assert_eq!(
+        ops.verify_mac(&token.t, &token.p_big, &q_big),
+        Ok(MacVerdict::Invalid)
+    );
+    let identity = RistrettoPoint::identity();
+    assert!(ops.verify_mac(&token.t, &identity, &identity).is_err());
+}
+
+#[test]
+pub fn local_key_operations_test() {
+    let ops = LocalKeyOperations::new(SecretKey::create(&mut OsRng));
+    issue_and_redeem(&ops, &PublicKey::create(&ops.sk));
+}
+
+#[test]
+pub fn unix_signer_test() {
+    let sk = SecretKey::create(&mut OsRng);
+    let pk = PublicKey::create(&sk);
+    let (client, mut server) = UnixStream::pair().unwrap();
+
+    // The key only exists on the signer thread
+    let signer = thread::spawn(move || {
+        let ops = LocalKeyOperations::new(sk);
+        serve_signer_connection(&mut server, &ops).unwrap();
+        ops.sk
+    });
+
+    let remote = UnixKeyOperations::from_stream(client);
+    issue_and_redeem(&remote, &pk);
+
+    // Signatures from the daemon redeem with the key directly too
+    let mut rng = OsRng;
+    let (ticket, receipt) = Ticket::create(&mut rng, &pk);
+    let bs = remote.sign(&ticket, PrivateMetadata::Bit(true)).unwrap();
+    let token = Token::create(&mut rng, &pk, &bs, &ticket, &receipt).unwrap();
+    drop(remote);
+    let sk = signer.join().unwrap();
+    assert_eq!(redeem_token(&token, &sk), RedemptionOutcome::Valid(true));
+}
+
+#[test]
+pub fn signer_malformed_request_test() {
+    let ops = LocalKeyOperations::new(SecretKey::create(&mut OsRng));
+    assert_eq!(handle_signer_request(&ops, &[]), vec![1]);
+    assert_eq!(handle_signer_request(&ops, &[3]), vec![1]);
+    assert_eq!(handle_signer_request(&ops, &[2; 33]), vec![1]);
+
+    // A MAC request is answered with the verdict alone
+    let mut request = vec![2];
+    request.extend_from_slice(&[0; 32]);
+    request.extend_from_slice(&ristretto_bytes(&PUBLIC_PARAMS.g_big.basepoint()));
+    request.extend_from_slice(&ristretto_bytes(&PUBLIC_PARAMS.h_big.basepoint()));
+    assert_eq!(handle_signer_request(&ops, &request), vec![0, 3]);
+
+    let (ticket, _) = Ticket::create(&mut OsRng, &ops.pk);
+    let mut request = vec![1];
+    request.extend_from_slice(&ticket.to_bytes());
+    request.push(3);
+    assert_eq!(handle_signer_request(&ops, &request), vec![1]);
+}
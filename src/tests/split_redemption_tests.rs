@@ -0,0 +1,122 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use curve25519_dalek_ng::ristretto::RistrettoPoint;
+use rand_core::OsRng;
+
+use crate::{
+    blind_sig::BlindSignature,
+    keys::{PublicKey, SecretKey},
+    server::redeem_token_split,
+    spent_tokens::SpentTokens,
+    split_redemption::{split_key, AnsweredTokens},
+    ticket::Ticket,
+    token::Token,
+};
+
+fn issue_token(pk: &PublicKey, sk: &SecretKey, b: bool) -> Token {
+    let mut rng = OsRng;
+    let (ticket, receipt) = Ticket::create(&mut rng, pk);
+    let bs = BlindSignature::create(&mut rng, pk, sk, &ticket, b);
+    Token::create(&mut rng, pk, &bs, &ticket, &receipt).unwrap()
+}
+
+#[test]
+pub fn split_redemption_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+    let (front_end, back_end) = split_key(&mut rng, &sk);
+    let mut spent = SpentTokens::new();
+    let mut answered = AnsweredTokens::new();
+
+    for b in [true, false] {
+        let token = issue_token(&pk, &sk, b);
+        let mut recovered = None;
+        let result = redeem_token_split(&mut rng, &token, &front_end, &mut spent, |blinded| {
+            let (bit, candidates) = back_end.recover_bit(blinded, &mut answered)?;
+            recovered = Some(bit);
+            Ok(candidates)
+        });
+
+        assert!(result.is_ok());
+        assert_eq!(recovered, Some(b));
+
+        // Spent after a valid redemption
+        let result = redeem_token_split(&mut rng, &token, &front_end, &mut spent, |blinded| {
+            back_end
+                .recover_bit(blinded, &mut answered)
+                .map(|(_, candidates)| candidates)
+        });
+        assert!(result.is_err());
+    }
+}
+
+#[test]
+pub fn split_redemption_fail_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+    let (front_end, back_end) = split_key(&mut rng, &sk);
+    let mut spent = SpentTokens::new();
+    let mut answered = AnsweredTokens::new();
+
+    // Token from another issuer
+    let sk2 = SecretKey::create(&mut rng);
+    let pk2 = PublicKey::create(&sk2);
+    let token = issue_token(&pk2, &sk2, true);
+    let result = redeem_token_split(&mut rng, &token, &front_end, &mut spent, |blinded| {
+        back_end
+            .recover_bit(blinded, &mut answered)
+            .map(|(_, candidates)| candidates)
+    });
+    assert!(result.is_err());
+    assert!(!spent.is_spent(&token.t));
+    assert!(answered.is_empty());
+
+    // The back-end gets a fresh blinding, but answers each token only once
+    let token = issue_token(&pk, &sk, true);
+    let first = front_end.blind(&mut rng, &token).unwrap();
+    let second = front_end.blind(&mut rng, &token).unwrap();
+    assert_ne!(first.p_big, second.p_big);
+    assert_eq!(first.nullifier, second.nullifier);
+    let (bit, candidates) = back_end.recover_bit(&first, &mut answered).unwrap();
+    assert!(bit);
+    assert!(candidates.contains(&first.r_big));
+    assert!(back_end.recover_bit(&second, &mut answered).is_err());
+}
+
+#[test]
+pub fn split_redemption_oracle_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+    let (front_end, back_end) = split_key(&mut rng, &sk);
+    let mut answered = AnsweredTokens::new();
+
+    // Arbitrary points, or two tokens combined into one query, are refused
+    let token = issue_token(&pk, &sk, true);
+    let other = issue_token(&pk, &sk, false);
+    let mut blinded = front_end.blind(&mut rng, &token).unwrap();
+    let blinded_other = front_end.blind(&mut rng, &other).unwrap();
+    blinded.p_big += blinded_other.p_big;
+    blinded.r_big += blinded_other.r_big;
+    assert!(back_end.recover_bit(&blinded, &mut answered).is_err());
+
+    let mut blinded = front_end.blind(&mut rng, &token).unwrap();
+    blinded.r_big = RistrettoPoint::random(&mut rng);
+    assert!(back_end.recover_bit(&blinded, &mut answered).is_err());
+
+    // Nor can the front-end swap in another nullifier
+    let mut blinded = front_end.blind(&mut rng, &token).unwrap();
+    blinded.nullifier = RistrettoPoint::random(&mut rng);
+    assert!(back_end.recover_bit(&blinded, &mut answered).is_err());
+    assert!(answered.is_empty());
+
+    // The candidates do not tell the front-end which bit matched
+    let blinded = front_end.blind(&mut rng, &other).unwrap();
+    let (bit, candidates) = back_end.recover_bit(&blinded, &mut answered).unwrap();
+    assert!(!bit);
+    assert_eq!(candidates.first, -candidates.second);
+    assert!(candidates.contains(&blinded.r_big));
+}
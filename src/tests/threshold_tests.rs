@@ -0,0 +1,200 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use curve25519_dalek_ng::scalar::Scalar;
+use rand_core::OsRng;
+
+use crate::{
+    keys::{PublicKey, SecretKey},
+    params::PUBLIC_PARAMS,
+    server::{redeem_token, RedemptionOutcome},
+    threshold::*,
+    ticket::Ticket,
+    token::Token,
+};
+
+// Round 1 on each of shares, each server with its own IssuedNonces
+fn round_one(
+    shares: &[SecretKeyShare],
+    ticket: &Ticket,
+) -> Result<(Vec<IssuanceNonce>, Vec<NonceCommitment>), ()> {
+    let signers: Vec<u32> = shares.iter().map(|share| share.index).collect();
+    let mut nonces = vec![];
+    let mut commitments = vec![];
+    for share in shares {
+        let (nonce, commitment) = IssuanceNonce::create(
+            &mut OsRng,
+            share,
+            &signers,
+            ticket,
+            &mut IssuedNonces::new(),
+        )?;
+        nonces.push(nonce);
+        commitments.push(commitment);
+    }
+
+    Ok((nonces, commitments))
+}
+
+// Servers check commitments against their own public key shares, the client
+// against pk_shares
+fn threshold_issue(
+    shares: &[SecretKeyShare],
+    pk_shares: &[PublicKeyShare],
+    pk: &PublicKey,
+    bits: &[bool],
+) -> Result<Token, ()> {
+    let mut rng = OsRng;
+    let (ticket, receipt) = Ticket::create(&mut rng, pk);
+    let server_pk_shares: Vec<PublicKeyShare> = shares.iter().map(PublicKeyShare::create).collect();
+
+    let (nonces, commitments) = round_one(shares, &ticket)?;
+
+    let partials: Vec<PartialBlindSignature> = shares
+        .iter()
+        .zip(nonces)
+        .zip(bits.iter().cycle())
+        .map(|((share, nonce), b)| {
+            PartialBlindSignature::create(
+                &mut rng,
+                share,
+                &server_pk_shares,
+                pk,
+                nonce,
+                &commitments,
+                &ticket,
+                *b,
+            )
+            .unwrap()
+        })
+        .collect();
+
+    combine_partial_signatures(
+        &mut rng,
+        pk,
+        pk_shares,
+        &commitments,
+        &partials,
+        &ticket,
+        &receipt,
+    )
+}
+
+#[test]
+pub fn threshold_issuance_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+    let shares = deal_shares(&mut rng, &sk, 3, 5);
+    let pk_shares: Vec<PublicKeyShare> = shares.iter().map(PublicKeyShare::create).collect();
+
+    for b in [true, false] {
+        let token = threshold_issue(&shares[..3], &pk_shares, &pk, &[b]).unwrap();
+        assert_eq!(redeem_token(&token, &sk), RedemptionOutcome::Valid(b));
+
+        let token = threshold_issue(&shares[1..5], &pk_shares, &pk, &[b]).unwrap();
+        assert_eq!(redeem_token(&token, &sk), RedemptionOutcome::Valid(b));
+    }
+}
+
+#[test]
+pub fn threshold_issuance_fail_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+    let shares = deal_shares(&mut rng, &sk, 3, 5);
+    let pk_shares: Vec<PublicKeyShare> = shares.iter().map(PublicKeyShare::create).collect();
+
+    // Too few servers to issue
+    let (ticket, _) = Ticket::create(&mut rng, &pk);
+    assert!(round_one(&shares[..2], &ticket).is_err());
+
+    // Nor can round 2 run on a subset of the signers from round 1
+    let (nonces, commitments) = round_one(&shares[..3], &ticket).unwrap();
+    let nonce = nonces.into_iter().next().unwrap();
+    assert!(PartialBlindSignature::create(
+        &mut rng,
+        &shares[0],
+        &pk_shares,
+        &pk,
+        nonce,
+        &commitments[..2],
+        &ticket,
+        true
+    )
+    .is_err());
+
+    // Shares of another key do not combine into a token for pk
+    let sk2 = SecretKey::create(&mut rng);
+    let shares2 = deal_shares(&mut rng, &sk2, 3, 5);
+    assert!(threshold_issue(&shares2[..3], &pk_shares, &pk, &[true]).is_err());
+}
+
+#[test]
+pub fn threshold_malicious_commitments_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+    let shares = deal_shares(&mut rng, &sk, 3, 5);
+    let pk_shares: Vec<PublicKeyShare> = shares.iter().map(PublicKeyShare::create).collect();
+    let (ticket, _) = Ticket::create(&mut rng, &pk);
+
+    let (mut nonces, mut commitments) = round_one(&shares[..3], &ticket).unwrap();
+
+    // A client replaces server 3's commitment so that U = a * G for an a it
+    // knows. The proofs it copies over do not match the new U_3.
+    let a = Scalar::random(&mut rng);
+    let rogue = (&a * &PUBLIC_PARAMS.g_big) - (commitments[0].u_big + commitments[1].u_big);
+    commitments[2].u_big = rogue;
+
+    let nonce = nonces.remove(0);
+    assert!(PartialBlindSignature::create(
+        &mut rng,
+        &shares[0],
+        &pk_shares,
+        &pk,
+        nonce,
+        &commitments,
+        &ticket,
+        true
+    )
+    .is_err());
+
+    // Nor can it stand in for server 3 with a key of its own
+    let sk2 = SecretKey::create(&mut rng);
+    let shares2 = deal_shares(&mut rng, &sk2, 3, 5);
+    let (_, forged) = IssuanceNonce::create(
+        &mut rng,
+        &shares2[2],
+        &[1, 2, 3],
+        &ticket,
+        &mut IssuedNonces::new(),
+    )
+    .unwrap();
+    let mut forged_commitments = commitments.clone();
+    forged_commitments[2] = forged;
+    let nonce = nonces.remove(0);
+    assert!(PartialBlindSignature::create(
+        &mut rng,
+        &shares[1],
+        &pk_shares,
+        &pk,
+        nonce,
+        &forged_commitments,
+        &ticket,
+        true
+    )
+    .is_err());
+}
+
+#[test]
+pub fn threshold_mixed_bits_test() {
+    let mut rng = OsRng;
+    let sk = SecretKey::create(&mut rng);
+    let pk = PublicKey::create(&sk);
+    let shares = deal_shares(&mut rng, &sk, 3, 5);
+    let pk_shares: Vec<PublicKeyShare> = shares.iter().map(PublicKeyShare::create).collect();
+
+    assert!(threshold_issue(&shares[..3], &pk_shares, &pk, &[true, true, false]).is_err());
+    assert!(threshold_issue(&shares[1..4], &pk_shares, &pk, &[false, true]).is_err());
+}
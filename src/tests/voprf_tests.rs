@@ -0,0 +1,188 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+use curve25519_dalek_ng::{ristretto::RistrettoPoint, scalar::Scalar};
+use rand_core::OsRng;
+
+use crate::{
+    server::redeem_voprf_token,
+    utils::{ristretto_bytes, ristretto_from_bytes},
+    voprf::{
+        evaluate, expand_message_xmd, VoprfEvaluation, VoprfProof, VoprfPublicKey, VoprfSecretKey,
+        VoprfTicket, VoprfToken,
+    },
+};
+
+fn from_hex(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+fn scalar_from_hex(hex: &str) -> Scalar {
+    Scalar::from_canonical_bytes(from_hex(hex).try_into().unwrap()).unwrap()
+}
+
+#[test]
+pub fn voprf_token_test() {
+    let mut rng = OsRng;
+    let sk = VoprfSecretKey::create(&mut rng);
+    let pk = VoprfPublicKey::create(&sk);
+
+    let (ticket, receipt) = VoprfTicket::create(&mut rng).unwrap();
+    let evaluation = VoprfEvaluation::create(&mut rng, &sk, &pk, &ticket);
+    let token = VoprfToken::create(&pk, &ticket, &receipt, &evaluation).unwrap();
+    assert!(redeem_voprf_token(&token, &sk).is_ok());
+
+    // Another key neither verifies the proof nor redeems the token
+    let other_sk = VoprfSecretKey::create(&mut rng);
+    let other_pk = VoprfPublicKey::create(&other_sk);
+    assert!(VoprfToken::create(&other_pk, &ticket, &receipt, &evaluation).is_err());
+    assert!(redeem_voprf_token(&token, &other_sk).is_err());
+
+    let mut forged = token;
+    forged.input[0] ^= 1;
+    assert!(redeem_voprf_token(&forged, &sk).is_err());
+}
+
+#[test]
+pub fn expand_message_xmd_test() {
+    // RFC 9380, Appendix K.3
+    let dst = b"QUUX-V01-CS02-with-expander-SHA512-256";
+    let mut output = [0u8; 32];
+    expand_message_xmd(b"", dst, &mut output);
+    assert_eq!(
+        output.to_vec(),
+        from_hex("6b9a7312411d92f921c6f68ca0b6380730a1a4d982c507211a90964c394179ba")
+    );
+}
+
+#[test]
+pub fn voprf_key_derivation_test() {
+    // RFC 9497, Appendix A.1.2: key pair derived from the test seed
+    let seed = [0xa3u8; 32];
+    let sk = VoprfSecretKey::derive(&seed, b"test key").unwrap();
+    let pk = VoprfPublicKey::create(&sk);
+    assert_eq!(
+        sk.k,
+        scalar_from_hex("e6f73f344b79b379f1a0dd37e07ff62e38d9f71345ce62ae3a9bc60b04ccd909")
+    );
+    assert_eq!(
+        ristretto_bytes(&pk.k_big).to_vec(),
+        from_hex("c803e2cc6b05fc15064549b5920659ca4a77b2cca6f04f6b357009335476ad4e")
+    );
+}
+
+fn point_from_hex(hex: &str) -> RistrettoPoint {
+    ristretto_from_bytes(&from_hex(hex)).unwrap()
+}
+
+// RFC 9497, Appendix A.1.2. The vectors' Blind values are not checked:
+// Blind is only exercised through Finalize, so BlindEvaluate, the proof and
+// Evaluate are checked against the published BlindedElement, Proof and Output.
+#[test]
+pub fn voprf_test_vector() {
+    let sk = VoprfSecretKey::derive(&[0xa3u8; 32], b"test key").unwrap();
+    let pk = VoprfPublicKey::create(&sk);
+    let r = scalar_from_hex("222a5e897cf59db8145db8d16e597e8facb80ae7d4e26d9881aa6f61d645fc0e");
+
+    let vectors: [(&[u8], &str, &str, &str, &str); 2] = [
+        (
+            &[0x00],
+            "863f330cc1a1259ed5a5998a23acfd37fb4351a793a5b3c090b642ddc439b945",
+            "aa8fa048764d5623868679402ff6108d2521884fa138cd7f9c7669a9a014267e",
+            "ddef93772692e535d1a53903db24367355cc2cc78de93b3be5a8ffcc6985dd06\
+             6d4346421d17bf5117a2a1ff0fcb2a759f58a539dfbe857a40bce4cf49ec600d",
+            "b58cfbe118e0cb94d79b5fd6a6dafb98764dff49c14e1770b566e42402da1a7d\
+             a4d8527693914139caee5bd03903af43a491351d23b430948dd50cde10d32b3c",
+        ),
+        (
+            &[0x5a; 17],
+            "cc0b2a350101881d8a4cba4c80241d74fb7dcbfde4a61fde2f91443c2bf9ef0c",
+            "60a59a57208d48aca71e9e850d22674b611f752bed48b36f7a91b372bd7ad468",
+            "401a0da6264f8cf45bb2f5264bc31e109155600babb3cd4e5af7d181a2c9dc0a\
+             67154fabf031fd936051dec80b0b6ae29c9503493dde7393b722eafdf5a50b02",
+            "8a9a2f3c7f085b65933594309041fc1898d42d0858e59f90814ae90571a6df60\
+             356f4610bf816f27afdd84f47719e480906d27ecd994985890e5f539e7ea74b6",
+        ),
+    ];
+
+    for (input, blinded_element, evaluated_element, proof, output) in vectors {
+        let ticket = VoprfTicket {
+            blinded_element: point_from_hex(blinded_element),
+        };
+        let evaluation = VoprfEvaluation::create_with_nonce(&sk, &pk, &ticket, &r);
+        assert_eq!(
+            ristretto_bytes(&evaluation.evaluated_element).to_vec(),
+            from_hex(evaluated_element)
+        );
+        assert_eq!(
+            [evaluation.pi.c.to_bytes(), evaluation.pi.s.to_bytes()].concat(),
+            from_hex(proof)
+        );
+        assert!(evaluation
+            .pi
+            .verify(
+                &pk.k_big,
+                &[ticket.blinded_element],
+                &[evaluation.evaluated_element]
+            )
+            .is_ok());
+        assert_eq!(evaluate(&sk, input).to_vec(), from_hex(output));
+    }
+}
+
+// RFC 9497, Appendix A.1.2, test vector 3 (batch size 2)
+#[test]
+pub fn voprf_batch_test_vector() {
+    let sk = VoprfSecretKey::derive(&[0xa3u8; 32], b"test key").unwrap();
+    let pk = VoprfPublicKey::create(&sk);
+    let r = scalar_from_hex("419c4f4f5052c53c45f3da494d2b67b220d02118e0857cdbcf037f9ea84bbe0c");
+
+    let blinded_elements = [
+        point_from_hex("863f330cc1a1259ed5a5998a23acfd37fb4351a793a5b3c090b642ddc439b945"),
+        point_from_hex("90a0145ea9da29254c3a56be4fe185465ebb3bf2a1801f7124bbbadac751e654"),
+    ];
+    let evaluated_elements = [
+        point_from_hex("aa8fa048764d5623868679402ff6108d2521884fa138cd7f9c7669a9a014267e"),
+        point_from_hex("cc5ac221950a49ceaa73c8db41b82c20372a4c8d63e5dded2db920b7eee36a2a"),
+    ];
+    for (blinded_element, evaluated_element) in blinded_elements.iter().zip(&evaluated_elements) {
+        assert_eq!(sk.k * blinded_element, *evaluated_element);
+    }
+
+    let pi = VoprfProof::create(&sk.k, &pk.k_big, &blinded_elements, &evaluated_elements, &r);
+    assert_eq!(
+        [pi.c.to_bytes(), pi.s.to_bytes()].concat(),
+        from_hex(
+            "cc203910175d786927eeb44ea847328047892ddf8590e723c37205cb74600b0a\
+             5ab5337c8eb4ceae0494c2cf89529dcf94572ed267473d567aeed6ab873dee08"
+        )
+    );
+    assert!(pi
+        .verify(&pk.k_big, &blinded_elements, &evaluated_elements)
+        .is_ok());
+    assert!(pi
+        .verify(
+            &pk.k_big,
+            &blinded_elements,
+            &[evaluated_elements[1], evaluated_elements[0]]
+        )
+        .is_err());
+
+    assert_eq!(
+        evaluate(&sk, &[0x00]).to_vec(),
+        from_hex(
+            "b58cfbe118e0cb94d79b5fd6a6dafb98764dff49c14e1770b566e42402da1a7d\
+             a4d8527693914139caee5bd03903af43a491351d23b430948dd50cde10d32b3c"
+        )
+    );
+    assert_eq!(
+        evaluate(&sk, &[0x5a; 17]).to_vec(),
+        from_hex(
+            "8a9a2f3c7f085b65933594309041fc1898d42d0858e59f90814ae90571a6df60\
+             356f4610bf816f27afdd84f47719e480906d27ecd994985890e5f539e7ea74b6"
+        )
+    );
+}
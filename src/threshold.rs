@@ -0,0 +1,765 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+// t-of-n threshold issuance. The scalars x, y, z, r_x and r_y are Shamir shared
+// across n servers. Issuance takes two rounds with any t of them:
+//
+//   1. Each server i picks d_i, ts_i and publishes U_i = d_i * G and ts_i,
+//      with a proof of knowledge of d_i and a signature under Z_i over the
+//      set of servers taking part. U = sum U_i, ts = sum ts_i, so no single
+//      server knows d.
+//   2. Each server checks every commitment and returns
+//      V_i = l_i * w_i * U + d_i * T, where l_i is its Lagrange coefficient
+//      and w_i = x_i + b * y_i + ts * z_i, together with a proof against its
+//      public key share.
+//
+// The commitment checks matter: if anyone could pick log_G U, V_i - d_i * T
+// would reveal w_i * G, and enough of those reveal X, Y and Z. The same holds
+// for a d_i used in two sessions: a co-signer that changes its own U_j between
+// them learns w_i * G from the difference. The round 1 nonces are derived
+// from T and the set of servers, so that with a stuck RNG they only repeat for
+// the same session, and each server keeps the sessions it has started in
+// IssuedNonces and refuses to start one twice.
+//
+// Each server also commits to the bit as C_b = b * C_y + mu * H under the full
+// key, with mu derived from a seed all servers share, and proves that its V_i
+// uses the same b. Honest servers produce the same C_b, so the client can tell
+// when one of them signed a different bit.
+//
+// The client verifies every partial proof and sums V = sum V_i =
+// d * (X + b * Y + ts * Z + T), which unblinds to a standard token.
+
+use std::collections::HashSet;
+
+use curve25519_dalek_ng::{ristretto::RistrettoPoint, scalar::Scalar, traits::Identity};
+use rand_core::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+
+use crate::{
+    dleq::KnowledgeProof,
+    keys::{PublicKey, SecretKey},
+    nonce::HedgedNonces,
+    params::PUBLIC_PARAMS,
+    ticket::{Receipt, Ticket},
+    token::Token,
+    utils::{non_zero_scalar, one_scalar, ristretto_bytes, scalar_bytes, zero_scalar},
+};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SecretKeyShare {
+    pub index: u32,
+    pub threshold: u32,
+    pub x: Scalar,
+    pub y: Scalar,
+    pub z: Scalar,
+    pub r_x: Scalar,
+    pub r_y: Scalar,
+    // Shared by all servers, never by clients; derives the bit commitment
+    pub bit_seed: [u8; 32],
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PublicKeyShare {
+    pub index: u32,
+    pub z_big: RistrettoPoint,
+    pub c_big_x: RistrettoPoint,
+    pub c_big_y: RistrettoPoint,
+}
+
+// Round 1 secret; consumed by the partial signature so it is never reused
+pub struct IssuanceNonce {
+    index: u32,
+    signers: Vec<u32>,
+    d: Scalar,
+    ts: Scalar,
+}
+
+// Round 1 message. pi proves knowledge of log_G U_i; sig is a Schnorr
+// signature under Z_i, so only server i can produce the commitment, and only
+// for these signers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NonceCommitment {
+    pub index: u32,
+    // The servers taking part, in ascending order
+    pub signers: Vec<u32>,
+    pub u_big: RistrettoPoint,
+    pub ts: Scalar,
+    pub pi: KnowledgeProof,
+    pub sig: KnowledgeProof,
+}
+
+pub struct PartialProof {
+    pub c_big: RistrettoPoint,
+    // The bit commitment under the full key, equal across honest servers
+    pub c_big_bit: RistrettoPoint,
+    pub e_zero: Scalar,
+    pub e_one: Scalar,
+    pub a_zero: Scalar,
+    pub a_one: Scalar,
+    pub a_zero_bit: Scalar,
+    pub a_one_bit: Scalar,
+    pub a_d: Scalar,
+    pub a_w: Scalar,
+    pub a_rho: Scalar,
+}
+
+// Round 2 message
+pub struct PartialBlindSignature {
+    pub index: u32,
+    pub v_big: RistrettoPoint,
+    pub pi: PartialProof,
+}
+
+// The (T, signers) sessions a server has started round 1 for
+#[derive(Debug, Default)]
+pub struct IssuedNonces {
+    issued: HashSet<[u8; 64]>,
+}
+
+// Splits the issuer key into n shares, any threshold of which can issue
+pub fn deal_shares<R>(rng: &mut R, sk: &SecretKey, threshold: u32, n: u32) -> Vec<SecretKeyShare>
+where
+    R: RngCore + CryptoRng,
+{
+    assert!(
+        threshold >= 1 && threshold <= n,
+        "threshold must be between 1 and n"
+    );
+
+    let polynomials: Vec<Vec<Scalar>> = [sk.x, sk.y, sk.z, sk.r_x, sk.r_y]
+        .iter()
+        .map(|secret| {
+            let mut coefficients = vec![*secret];
+            for _ in 1..threshold {
+                coefficients.push(Scalar::random(rng));
+            }
+            coefficients
+        })
+        .collect();
+
+    let mut bit_seed = [0u8; 32];
+    rng.fill_bytes(&mut bit_seed);
+
+    (1..=n)
+        .map(|index| {
+            let evaluated: Vec<Scalar> = polynomials
+                .iter()
+                .map(|coefficients| evaluate_polynomial(coefficients, index))
+                .collect();
+
+            SecretKeyShare {
+                index,
+                threshold,
+                x: evaluated[0],
+                y: evaluated[1],
+                z: evaluated[2],
+                r_x: evaluated[3],
+                r_y: evaluated[4],
+                bit_seed,
+            }
+        })
+        .collect()
+}
+
+impl PublicKeyShare {
+    pub fn create(share: &SecretKeyShare) -> PublicKeyShare {
+        PublicKeyShare {
+            index: share.index,
+            z_big: &PUBLIC_PARAMS.g_big * &share.z,
+            c_big_x: (&share.x * &PUBLIC_PARAMS.g_big) + (&share.r_x * &PUBLIC_PARAMS.h_big),
+            c_big_y: (&share.y * &PUBLIC_PARAMS.g_big) + (&share.r_y * &PUBLIC_PARAMS.h_big),
+        }
+    }
+}
+
+impl IssuanceNonce {
+    // Round 1 for the ticket t with the given signers, which must include
+    // this server. Fails if this server already started the same session.
+    pub fn create<R>(
+        rng: &mut R,
+        share: &SecretKeyShare,
+        signers: &[u32],
+        t: &Ticket,
+        issued: &mut IssuedNonces,
+    ) -> Result<(IssuanceNonce, NonceCommitment), ()>
+    where
+        R: RngCore + CryptoRng,
+    {
+        let mut signers = signers.to_vec();
+        signers.sort_unstable();
+        if signers.len() < share.threshold as usize
+            || signers.binary_search(&share.index).is_err()
+            || signers.contains(&0)
+            || signers.windows(2).any(|pair| pair[0] == pair[1])
+        {
+            return Err(());
+        }
+        let session = signers_bytes(&signers);
+        if !issued.mark_issued(t, &signers) {
+            return Err(());
+        }
+
+        let mut nonces = HedgedNonces::with_secrets(
+            rng,
+            b"MacTok-ThresholdNonce",
+            &share_secrets(share),
+            &[&share.index.to_be_bytes(), &session, &t.to_bytes()],
+        );
+
+        let nonce = IssuanceNonce {
+            index: share.index,
+            signers,
+            d: nonces.non_zero_scalar(),
+            ts: nonces.non_zero_scalar(),
+        };
+        let u_big = &nonce.d * &PUBLIC_PARAMS.g_big;
+        let z_big = &share.z * &PUBLIC_PARAMS.g_big;
+        let context = commitment_context(share.index, &nonce.signers, &u_big, &nonce.ts);
+        let g_big = PUBLIC_PARAMS.g_big.basepoint();
+
+        let commitment = NonceCommitment {
+            index: share.index,
+            signers: nonce.signers.clone(),
+            u_big,
+            ts: nonce.ts,
+            pi: KnowledgeProof::create_with_nonce(
+                &nonces.non_zero_scalar(),
+                &nonce.d,
+                &g_big,
+                &u_big,
+                &context,
+            ),
+            sig: KnowledgeProof::create_with_nonce(
+                &nonces.non_zero_scalar(),
+                &share.z,
+                &g_big,
+                &z_big,
+                &context,
+            ),
+        };
+
+        Ok((nonce, commitment))
+    }
+}
+
+impl IssuedNonces {
+    pub fn new() -> IssuedNonces {
+        IssuedNonces::default()
+    }
+
+    // Returns false if the session was already started
+    pub fn mark_issued(&mut self, t: &Ticket, signers: &[u32]) -> bool {
+        let mut hasher = Sha512::new();
+        hasher.update(b"MacTok-ThresholdSession");
+        hasher.update(t.to_bytes());
+        hasher.update(signers_bytes(signers));
+
+        let mut session = [0u8; 64];
+        session.copy_from_slice(&hasher.finalize());
+        self.issued.insert(session)
+    }
+
+    pub fn len(&self) -> usize {
+        self.issued.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.issued.is_empty()
+    }
+}
+
+impl NonceCommitment {
+    pub fn verify(&self, pk_shares: &[PublicKeyShare]) -> Result<(), ()> {
+        let pk_share = pk_shares
+            .iter()
+            .find(|pk_share| pk_share.index == self.index)
+            .ok_or(())?;
+        let context = commitment_context(self.index, &self.signers, &self.u_big, &self.ts);
+        let g_big = PUBLIC_PARAMS.g_big.basepoint();
+
+        self.pi.verify(&g_big, &self.u_big, &context)?;
+        self.sig.verify(&g_big, &pk_share.z_big, &context)
+    }
+}
+
+impl PartialBlindSignature {
+    #[allow(clippy::too_many_arguments)]
+    pub fn create<R>(
+        rng: &mut R,
+        share: &SecretKeyShare,
+        pk_shares: &[PublicKeyShare],
+        pk: &PublicKey,
+        nonce: IssuanceNonce,
+        commitments: &[NonceCommitment],
+        t: &Ticket,
+        b: bool,
+    ) -> Result<PartialBlindSignature, ()>
+    where
+        R: RngCore + CryptoRng,
+    {
+        if nonce.index != share.index {
+            return Err(());
+        }
+        if commitments.len() < share.threshold as usize {
+            return Err(());
+        }
+        let pk_share = pk_shares
+            .iter()
+            .find(|pk_share| pk_share.index == share.index)
+            .ok_or(())?;
+
+        // Our own commitment must be part of U, and every other one must come
+        // from its server, so that nobody knows log_G U
+        let own = commitments
+            .iter()
+            .find(|commitment| commitment.index == share.index)
+            .ok_or(())?;
+        if own.u_big != &nonce.d * &PUBLIC_PARAMS.g_big
+            || own.ts != nonce.ts
+            || own.signers != nonce.signers
+        {
+            return Err(());
+        }
+
+        let (u_big, ts) = aggregate_commitments(commitments, pk_shares)?;
+        let lambda = lagrange_coefficient(share.index, commitments)?;
+
+        let mut bytes = [0u8; 32];
+        bytes[0] = b.into();
+        let scalar_b = Scalar::from_bytes_mod_order(bytes);
+
+        // V_i = l_i * w_i * U + d_i * T
+        let w = share.x + (scalar_b * share.y) + (ts * share.z);
+        let v_big = ((lambda * w) * u_big) + (nonce.d * t.t_big);
+
+        let statement = PartialStatement {
+            pk,
+            pk_share,
+            own,
+            u_big: &u_big,
+            ts: &ts,
+            lambda: &lambda,
+            t,
+            v_big: &v_big,
+        };
+        let pi = PartialProof::create(rng, share, &statement, &nonce, &scalar_b, &w);
+
+        Ok(PartialBlindSignature {
+            index: share.index,
+            v_big,
+            pi,
+        })
+    }
+}
+
+// What a partial proof is about; everything here is public
+struct PartialStatement<'a> {
+    pk: &'a PublicKey,
+    pk_share: &'a PublicKeyShare,
+    own: &'a NonceCommitment,
+    u_big: &'a RistrettoPoint,
+    ts: &'a Scalar,
+    lambda: &'a Scalar,
+    t: &'a Ticket,
+    v_big: &'a RistrettoPoint,
+}
+
+impl PartialProof {
+    fn create<R>(
+        rng: &mut R,
+        share: &SecretKeyShare,
+        statement: &PartialStatement,
+        nonce: &IssuanceNonce,
+        scalar_b: &Scalar,
+        w: &Scalar,
+    ) -> PartialProof
+    where
+        R: RngCore + CryptoRng,
+    {
+        let pk_share = statement.pk_share;
+
+        // All nonces are hedged from the share, the signing secrets, the
+        // transcript and fresh randomness
+        let mut secrets = share_secrets(share);
+        secrets.extend([&nonce.d, scalar_b, w]);
+        let mut nonces = HedgedNonces::with_secrets(
+            rng,
+            b"MacTok-ThresholdPartialProof",
+            &secrets,
+            &[
+                &share.index.to_be_bytes(),
+                &ristretto_bytes(statement.u_big),
+                &scalar_bytes(statement.ts),
+                &statement.t.to_bytes(),
+                &ristretto_bytes(statement.v_big),
+            ],
+        );
+
+        // e_one_minus_b, a_one_minus_b, a_one_minus_b_bit <-- ZZ_p
+        let e_one_minus_b = nonces.scalar();
+        let a_one_minus_b = nonces.scalar();
+        let a_one_minus_b_bit = nonces.scalar();
+
+        // r_mu, r_mu_bit, r_d, r_w, r_rho <-- ZZ_p
+        let r_mu = nonces.scalar();
+        let r_mu_bit = nonces.scalar();
+        let r_d = nonces.scalar();
+        let r_w = nonces.scalar();
+        let r_rho = nonces.scalar();
+
+        // C <-- b * C_y_i + mu * H
+        let mu = nonces.non_zero_scalar();
+        let c_big = (scalar_b * pk_share.c_big_y) + (&mu * &PUBLIC_PARAMS.h_big);
+
+        // C_b <-- b * C_y + mu_b * H, with mu_b the same on every server
+        let mu_bit = bit_commitment_scalar(share, statement);
+        let c_big_bit = (scalar_b * statement.pk.c_big_y) + (&mu_bit * &PUBLIC_PARAMS.h_big);
+
+        // Real branch: C_b <-- r_mu * H, C'_b <-- r_mu_bit * H
+        let c_big_b = &r_mu * &PUBLIC_PARAMS.h_big;
+        let c_big_b_bit = &r_mu_bit * &PUBLIC_PARAMS.h_big;
+
+        // Simulated branch, for both commitments with the same challenge:
+        // C_one_minus_b <-- a_one_minus_b * H - e_one_minus_b * (C - (1 - b) * C_y_i)
+        let one_minus_b = one_scalar() - scalar_b;
+        let point = c_big - (one_minus_b * pk_share.c_big_y);
+        let c_big_one_minus_b = (&a_one_minus_b * &PUBLIC_PARAMS.h_big) - (e_one_minus_b * point);
+        let point = c_big_bit - (one_minus_b * statement.pk.c_big_y);
+        let c_big_one_minus_b_bit =
+            (&a_one_minus_b_bit * &PUBLIC_PARAMS.h_big) - (e_one_minus_b * point);
+
+        let is_zero = scalar_b == &zero_scalar();
+        let (c_big_zero, c_big_one) = if is_zero {
+            (c_big_b, c_big_one_minus_b)
+        } else {
+            (c_big_one_minus_b, c_big_b)
+        };
+        let (c_big_zero_bit, c_big_one_bit) = if is_zero {
+            (c_big_b_bit, c_big_one_minus_b_bit)
+        } else {
+            (c_big_one_minus_b_bit, c_big_b_bit)
+        };
+
+        // C_d <-- r_d * G
+        let c_big_d = &r_d * &PUBLIC_PARAMS.g_big;
+
+        // C_v <-- l_i * r_w * U + r_d * T
+        let c_big_v = ((statement.lambda * r_w) * statement.u_big) + (r_d * statement.t.t_big);
+
+        // C_rho <-- r_w * G + r_rho * H
+        let c_big_rho = (&r_w * &PUBLIC_PARAMS.g_big) + (&r_rho * &PUBLIC_PARAMS.h_big);
+
+        let e = partial_challenge(
+            statement,
+            &[
+                &c_big,
+                &c_big_bit,
+                &c_big_zero,
+                &c_big_one,
+                &c_big_zero_bit,
+                &c_big_one_bit,
+                &c_big_d,
+                &c_big_v,
+                &c_big_rho,
+            ],
+        );
+
+        // e_b <-- e - e_one_minus_b; a_b <-- r_mu + e_b * mu
+        let e_b = e - e_one_minus_b;
+        let a_b = r_mu + (e_b * mu);
+        let a_b_bit = r_mu_bit + (e_b * mu_bit);
+
+        // rho <-- r_x_i + b * r_y_i + mu
+        let rho = share.r_x + (scalar_b * share.r_y) + mu;
+
+        let a_d = r_d + (e * nonce.d);
+        let a_w = r_w + (e * w);
+        let a_rho = r_rho + (e * rho);
+
+        let (e_zero, e_one, a_zero, a_one, a_zero_bit, a_one_bit) = if is_zero {
+            (
+                e_b,
+                e_one_minus_b,
+                a_b,
+                a_one_minus_b,
+                a_b_bit,
+                a_one_minus_b_bit,
+            )
+        } else {
+            (
+                e_one_minus_b,
+                e_b,
+                a_one_minus_b,
+                a_b,
+                a_one_minus_b_bit,
+                a_b_bit,
+            )
+        };
+
+        PartialProof {
+            c_big,
+            c_big_bit,
+            e_zero,
+            e_one,
+            a_zero,
+            a_one,
+            a_zero_bit,
+            a_one_bit,
+            a_d,
+            a_w,
+            a_rho,
+        }
+    }
+
+    fn verify(&self, statement: &PartialStatement) -> Result<(), ()> {
+        let pk_share = statement.pk_share;
+        let h_big = &PUBLIC_PARAMS.h_big;
+
+        // C_0 <-- a_0 * H - e_0 * C
+        let c_big_zero = (&self.a_zero * h_big) - (self.e_zero * self.c_big);
+
+        // C_1 <-- a_1 * H - e_1 * (C - C_y_i)
+        let c_big_one = (&self.a_one * h_big) - (self.e_one * (self.c_big - pk_share.c_big_y));
+
+        // C'_0 <-- a'_0 * H - e_0 * C_b; C'_1 <-- a'_1 * H - e_1 * (C_b - C_y)
+        let c_big_zero_bit = (&self.a_zero_bit * h_big) - (self.e_zero * self.c_big_bit);
+        let c_big_one_bit =
+            (&self.a_one_bit * h_big) - (self.e_one * (self.c_big_bit - statement.pk.c_big_y));
+
+        // e <-- e_0 + e_1
+        let e = self.e_zero + self.e_one;
+
+        // C_d <-- a_d * G - e * U_i
+        let c_big_d = (&self.a_d * &PUBLIC_PARAMS.g_big) - (e * statement.own.u_big);
+
+        // C_v <-- l_i * a_w * U + a_d * T - e * V_i
+        let c_big_v = ((statement.lambda * self.a_w) * statement.u_big)
+            + (self.a_d * statement.t.t_big)
+            - (e * statement.v_big);
+
+        // C_rho <-- a_w * G + a_rho * H - e * (C_x_i + C + ts * Z_i)
+        let aux = pk_share.c_big_x + self.c_big + (statement.ts * pk_share.z_big);
+        let c_big_rho = (&self.a_w * &PUBLIC_PARAMS.g_big) + (&self.a_rho * h_big) - (e * aux);
+
+        let e_verify = partial_challenge(
+            statement,
+            &[
+                &self.c_big,
+                &self.c_big_bit,
+                &c_big_zero,
+                &c_big_one,
+                &c_big_zero_bit,
+                &c_big_one_bit,
+                &c_big_d,
+                &c_big_v,
+                &c_big_rho,
+            ],
+        );
+
+        if e_verify != e {
+            return Err(());
+        }
+
+        Ok(())
+    }
+}
+
+// Verifies every partial signature and unblinds the combined signature. The
+// public key shares must reconstruct pk for the participating servers.
+#[allow(clippy::too_many_arguments)]
+pub fn combine_partial_signatures<R>(
+    rng: &mut R,
+    pk: &PublicKey,
+    pk_shares: &[PublicKeyShare],
+    commitments: &[NonceCommitment],
+    partials: &[PartialBlindSignature],
+    ticket: &Ticket,
+    receipt: &Receipt,
+) -> Result<Token, ()>
+where
+    R: RngCore + CryptoRng,
+{
+    if partials.len() != commitments.len() {
+        return Err(());
+    }
+
+    let (u_big, ts) = aggregate_commitments(commitments, pk_shares)?;
+    if u_big == RistrettoPoint::identity() {
+        return Err(());
+    }
+
+    // Every server must have committed to the same bit
+    let c_big_bit = partials.first().ok_or(())?.pi.c_big_bit;
+    if partials
+        .iter()
+        .any(|partial| partial.pi.c_big_bit != c_big_bit)
+    {
+        return Err(());
+    }
+
+    let mut z_big = RistrettoPoint::identity();
+    let mut c_big_x = RistrettoPoint::identity();
+    let mut c_big_y = RistrettoPoint::identity();
+    let mut v_big = RistrettoPoint::identity();
+
+    for partial in partials {
+        let own = commitments
+            .iter()
+            .find(|commitment| commitment.index == partial.index)
+            .ok_or(())?;
+        let pk_share = pk_shares
+            .iter()
+            .find(|pk_share| pk_share.index == partial.index)
+            .ok_or(())?;
+        let lambda = lagrange_coefficient(partial.index, commitments)?;
+
+        partial.pi.verify(&PartialStatement {
+            pk,
+            pk_share,
+            own,
+            u_big: &u_big,
+            ts: &ts,
+            lambda: &lambda,
+            t: ticket,
+            v_big: &partial.v_big,
+        })?;
+
+        z_big += lambda * pk_share.z_big;
+        c_big_x += lambda * pk_share.c_big_x;
+        c_big_y += lambda * pk_share.c_big_y;
+        v_big += partial.v_big;
+    }
+
+    if z_big != pk.z_big || c_big_x != pk.c_big_x || c_big_y != pk.c_big_y {
+        return Err(());
+    }
+
+    let c = non_zero_scalar(rng);
+    let p_big = u_big * c;
+    let q_big = (v_big - (receipt.r * u_big)) * c;
+    let t = receipt.tc + ts;
+
+    Ok(Token { t, p_big, q_big })
+}
+
+fn evaluate_polynomial(coefficients: &[Scalar], index: u32) -> Scalar {
+    let point = Scalar::from(index as u64);
+    coefficients
+        .iter()
+        .rev()
+        .fold(zero_scalar(), |acc, coefficient| {
+            (acc * point) + coefficient
+        })
+}
+
+// U = sum U_j and ts = sum ts_j over distinct, non-zero indices, each
+// commitment signed by its server for exactly these signers
+fn aggregate_commitments(
+    commitments: &[NonceCommitment],
+    pk_shares: &[PublicKeyShare],
+) -> Result<(RistrettoPoint, Scalar), ()> {
+    let mut u_big = RistrettoPoint::identity();
+    let mut ts = zero_scalar();
+
+    let mut signers: Vec<u32> = commitments
+        .iter()
+        .map(|commitment| commitment.index)
+        .collect();
+    signers.sort_unstable();
+
+    for (i, commitment) in commitments.iter().enumerate() {
+        if commitment.index == 0
+            || commitment.signers != signers
+            || commitments[..i]
+                .iter()
+                .any(|other| other.index == commitment.index)
+        {
+            return Err(());
+        }
+        commitment.verify(pk_shares)?;
+        u_big += commitment.u_big;
+        ts += commitment.ts;
+    }
+
+    Ok((u_big, ts))
+}
+
+// l_i = prod_{j != i} j / (j - i) over the participating indices
+fn lagrange_coefficient(index: u32, commitments: &[NonceCommitment]) -> Result<Scalar, ()> {
+    let i = Scalar::from(index as u64);
+    let mut numerator = one_scalar();
+    let mut denominator = one_scalar();
+
+    for commitment in commitments {
+        if commitment.index == index {
+            continue;
+        }
+        let j = Scalar::from(commitment.index as u64);
+        numerator *= j;
+        denominator *= j - i;
+    }
+
+    if denominator == zero_scalar() {
+        return Err(());
+    }
+
+    Ok(numerator * denominator.invert())
+}
+
+fn partial_challenge(statement: &PartialStatement, points: &[&RistrettoPoint]) -> Scalar {
+    let pk_share = statement.pk_share;
+    let mut hasher = Sha512::new();
+    hasher.update(b"MacTok-ThresholdIssuance");
+    hasher.update(pk_share.index.to_be_bytes());
+    hasher.update(ristretto_bytes(&pk_share.c_big_x));
+    hasher.update(ristretto_bytes(&pk_share.c_big_y));
+    hasher.update(ristretto_bytes(&pk_share.z_big));
+    hasher.update(ristretto_bytes(&statement.pk.c_big_y));
+    hasher.update(ristretto_bytes(&statement.own.u_big));
+    hasher.update(ristretto_bytes(statement.u_big));
+    hasher.update(scalar_bytes(statement.ts));
+    hasher.update(ristretto_bytes(&statement.t.t_big));
+    hasher.update(ristretto_bytes(statement.v_big));
+    for point in points {
+        hasher.update(ristretto_bytes(point));
+    }
+
+    Scalar::from_hash(hasher)
+}
+
+// mu for the bit commitment; every server derives the same value for one
+// issuance from the shared seed
+fn bit_commitment_scalar(share: &SecretKeyShare, statement: &PartialStatement) -> Scalar {
+    let mut hasher = Sha512::new();
+    hasher.update(b"MacTok-ThresholdBit");
+    hasher.update(share.bit_seed);
+    hasher.update(ristretto_bytes(statement.u_big));
+    hasher.update(scalar_bytes(statement.ts));
+    hasher.update(ristretto_bytes(&statement.t.t_big));
+
+    Scalar::from_hash(hasher)
+}
+
+fn share_secrets(share: &SecretKeyShare) -> Vec<&Scalar> {
+    vec![&share.x, &share.y, &share.z, &share.r_x, &share.r_y]
+}
+
+fn signers_bytes(signers: &[u32]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(4 + 4 * signers.len());
+    bytes.extend_from_slice(&(signers.len() as u32).to_be_bytes());
+    for index in signers {
+        bytes.extend_from_slice(&index.to_be_bytes());
+    }
+    bytes
+}
+
+fn commitment_context(index: u32, signers: &[u32], u_big: &RistrettoPoint, ts: &Scalar) -> Vec<u8> {
+    let mut context = Vec::with_capacity(16 + 4 + 4 + 4 * signers.len() + 32 + 32);
+    context.extend_from_slice(b"MacTok-Threshold");
+    context.extend_from_slice(&index.to_be_bytes());
+    context.extend_from_slice(&signers_bytes(signers));
+    context.extend_from_slice(&ristretto_bytes(u_big));
+    context.extend_from_slice(&scalar_bytes(ts));
+    context
+}
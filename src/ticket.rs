@@ -6,7 +6,11 @@ use curve25519_dalek_ng::{ristretto::RistrettoPoint, scalar::Scalar};
 use rand_core::{CryptoRng, RngCore};
 use serde::{Deserialize, Serialize}; // G
 
-use crate::{keys::PublicKey, utils::non_zero_scalar};
+use crate::{
+    client_binding::{BindingPublicKey, ClientKey},
+    keys::PublicKey,
+    utils::{non_zero_scalar, ristretto_bytes, ristretto_from_bytes},
+};
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Ticket {
@@ -36,4 +40,30 @@ impl Ticket {
 
         (ticket, receipt)
     }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        ristretto_bytes(&self.t_big)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Ticket, ()> {
+        Ok(Ticket {
+            t_big: ristretto_from_bytes(bytes)?,
+        })
+    }
+
+    pub fn create_bound<R>(
+        rng: &mut R,
+        pk: &PublicKey,
+        bpk: &BindingPublicKey,
+        client_key: &ClientKey,
+    ) -> (Ticket, Receipt)
+    where
+        R: RngCore + CryptoRng,
+    {
+        // T = tc * Z + r * G + k * W; r keeps the client key hidden from the issuer
+        let (mut ticket, receipt) = Ticket::create(rng, pk);
+        ticket.t_big += client_key.k * bpk.w_big;
+
+        (ticket, receipt)
+    }
 }
@@ -8,7 +8,9 @@ use crate::{
     blind_sig::BlindSignature,
     keys::PublicKey,
     ticket::{Receipt, Ticket},
-    utils::non_zero_scalar,
+    utils::{
+        non_zero_scalar, ristretto_bytes, ristretto_from_bytes, scalar_bytes, scalar_from_bytes,
+    },
     verifier_client,
 };
 
@@ -47,4 +49,27 @@ impl Token {
         let token = Token { t, p_big, q_big };
         Ok(token)
     }
+
+    pub const SIZE: usize = 96;
+
+    // t || P || Q
+    pub fn to_bytes(&self) -> [u8; Token::SIZE] {
+        let mut bytes = [0u8; Token::SIZE];
+        bytes[..32].copy_from_slice(&scalar_bytes(&self.t));
+        bytes[32..64].copy_from_slice(&ristretto_bytes(&self.p_big));
+        bytes[64..].copy_from_slice(&ristretto_bytes(&self.q_big));
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Token, ()> {
+        if bytes.len() != Token::SIZE {
+            return Err(());
+        }
+
+        Ok(Token {
+            t: scalar_from_bytes(&bytes[..32])?,
+            p_big: ristretto_from_bytes(&bytes[32..64])?,
+            q_big: ristretto_from_bytes(&bytes[64..])?,
+        })
+    }
 }
@@ -1,7 +1,10 @@
 // Copyright (c) Microsoft Corporation. All rights reserved.
 // Licensed under the MIT license.
 
-use curve25519_dalek_ng::{ristretto::RistrettoPoint, scalar::Scalar};
+use curve25519_dalek_ng::{
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    scalar::Scalar,
+};
 use rand_core::{CryptoRng, RngCore};
 
 pub fn non_zero_scalar<R>(rng: &mut R) -> Scalar
@@ -38,3 +41,52 @@ pub fn ristretto_bytes(ristretto_point: &RistrettoPoint) -> [u8; 32] {
 pub fn scalar_bytes(ristretto_scalar: &Scalar) -> [u8; 32] {
     return ristretto_scalar.to_bytes();
 }
+
+pub fn ristretto_from_bytes(bytes: &[u8]) -> Result<RistrettoPoint, ()> {
+    let bytes: [u8; 32] = bytes.try_into().map_err(|_| ())?;
+    CompressedRistretto(bytes).decompress().ok_or(())
+}
+
+pub fn scalar_from_bytes(bytes: &[u8]) -> Result<Scalar, ()> {
+    let bytes: [u8; 32] = bytes.try_into().map_err(|_| ())?;
+    Scalar::from_canonical_bytes(bytes).ok_or(())
+}
+
+// Sequential reader for length-prefixed wire formats
+pub struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> ByteReader<'a> {
+        ByteReader { bytes }
+    }
+
+    pub fn read(&mut self, len: usize) -> Result<&'a [u8], ()> {
+        if self.bytes.len() < len {
+            return Err(());
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, ()> {
+        Ok(u16::from_be_bytes(self.read_array()?))
+    }
+
+    pub fn read_array<const N: usize>(&mut self) -> Result<[u8; N], ()> {
+        Ok(self.read(N)?.try_into().expect("read returned N bytes"))
+    }
+
+    pub fn read_string(&mut self, len: usize) -> Result<String, ()> {
+        String::from_utf8(self.read(len)?.to_vec()).map_err(|_| ())
+    }
+
+    pub fn finish(&self) -> Result<(), ()> {
+        if !self.bytes.is_empty() {
+            return Err(());
+        }
+        Ok(())
+    }
+}
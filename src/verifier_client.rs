@@ -17,8 +17,13 @@ pub fn verify_proof(pk: &PublicKey, ticket: &Ticket, bs: &BlindSignature) -> Res
     let c_big_one =
         (&bs.pi.a_one * &PUBLIC_PARAMS.h_big) - (&bs.pi.e_one * (&bs.pi.c_big - &pk.c_big_y));
 
-    // e <-- e_0 + e_1
-    let e = &bs.pi.e_zero + &bs.pi.e_one;
+    // C_2 <-- a_2 * H - e_2 * (C - 2 * C_y)
+    let two_c_big_y = Scalar::from(2u64) * pk.c_big_y;
+    let c_big_two =
+        (&bs.pi.a_two * &PUBLIC_PARAMS.h_big) - (bs.pi.e_two * (bs.pi.c_big - two_c_big_y));
+
+    // e <-- e_0 + e_1 + e_2
+    let e = bs.pi.e_zero + bs.pi.e_one + bs.pi.e_two;
 
     // C_d = a_d * U + e * G
     let c_big_d = (&bs.pi.a_d * &bs.u_big) + (&e * &PUBLIC_PARAMS.g_big);
@@ -31,7 +36,7 @@ pub fn verify_proof(pk: &PublicKey, ticket: &Ticket, bs: &BlindSignature) -> Res
     // C_w = a_d * V + a_w * G + e * T; TODO: reuse a_d * V
     let c_big_w = a_d_v_big + (&bs.pi.a_w * &PUBLIC_PARAMS.g_big) + (&e * &ticket.t_big);
 
-    // e_verify <-- Hash(G, H, C_x, C_y, C, C_0, C_1, C_d, C_rho, C_w)
+    // e_verify <-- Hash(G, H, C_x, C_y, C, C_0, C_1, C_2, C_d, C_rho, C_w)
     let mut hasher = Sha512::new();
     hasher.update(ristretto_bytes(&pk.c_big_x));
     hasher.update(ristretto_bytes(&pk.c_big_y));
@@ -42,6 +47,7 @@ pub fn verify_proof(pk: &PublicKey, ticket: &Ticket, bs: &BlindSignature) -> Res
     hasher.update(ristretto_bytes(&bs.pi.c_big));
     hasher.update(ristretto_bytes(&c_big_zero));
     hasher.update(ristretto_bytes(&c_big_one));
+    hasher.update(ristretto_bytes(&c_big_two));
     hasher.update(ristretto_bytes(&c_big_d));
     hasher.update(ristretto_bytes(&c_big_rho));
     hasher.update(ristretto_bytes(&c_big_w));
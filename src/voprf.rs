@@ -0,0 +1,389 @@
+// Copyright (c) Microsoft Corporation. All rights reserved.
+// Licensed under the MIT license.
+
+// Plain anonymous tokens without private metadata: the VOPRF mode (0x01) of
+// RFC 9497 over ristretto255 with SHA-512. A token is (input, output) where
+// output = Hash(input, sk * HashToGroup(input)); the server redeems it by
+// re-evaluating the PRF. G is the ristretto255 base point from params, so the
+// keys interoperate with other RFC 9497 implementations.
+
+use curve25519_dalek_ng::{ristretto::RistrettoPoint, scalar::Scalar, traits::Identity};
+use rand_core::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+use subtle::ConstantTimeEq;
+
+use crate::{
+    nonce::HedgedNonces,
+    params::PUBLIC_PARAMS,
+    utils::{non_zero_scalar, ristretto_bytes},
+};
+
+const CONTEXT_STRING: &[u8] = b"OPRFV1-\x01-ristretto255-SHA512";
+
+// Length of the random token input chosen by the client
+pub const INPUT_LENGTH: usize = 32;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct VoprfSecretKey {
+    pub k: Scalar,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct VoprfPublicKey {
+    pub k_big: RistrettoPoint,
+}
+
+// Proof that log_G pkS = log_M Z for the composite (M, Z) of a batch
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoprfProof {
+    pub c: Scalar,
+    pub s: Scalar,
+}
+
+pub struct VoprfTicket {
+    pub blinded_element: RistrettoPoint,
+}
+
+pub struct VoprfReceipt {
+    pub input: [u8; INPUT_LENGTH],
+    pub blind: Scalar,
+}
+
+pub struct VoprfEvaluation {
+    pub evaluated_element: RistrettoPoint,
+    pub pi: VoprfProof,
+}
+
+pub struct VoprfToken {
+    pub input: [u8; INPUT_LENGTH],
+    pub output: [u8; 64],
+}
+
+impl VoprfSecretKey {
+    pub fn create<R>(rng: &mut R) -> VoprfSecretKey
+    where
+        R: RngCore + CryptoRng,
+    {
+        VoprfSecretKey {
+            k: non_zero_scalar(rng),
+        }
+    }
+
+    // DeriveKeyPair from RFC 9497, Section 3.2.1
+    pub fn derive(seed: &[u8; 32], info: &[u8]) -> Result<VoprfSecretKey, ()> {
+        if info.len() > u16::MAX as usize {
+            return Err(());
+        }
+
+        let mut derive_input = Vec::with_capacity(32 + 2 + info.len() + 1);
+        derive_input.extend_from_slice(seed);
+        derive_input.extend_from_slice(&(info.len() as u16).to_be_bytes());
+        derive_input.extend_from_slice(info);
+
+        let dst = [b"DeriveKeyPair".as_ref(), CONTEXT_STRING].concat();
+        for counter in 0..=255u8 {
+            derive_input.push(counter);
+            let k = hash_to_scalar_with_dst(&derive_input, &dst);
+            derive_input.pop();
+            if k != Scalar::zero() {
+                return Ok(VoprfSecretKey { k });
+            }
+        }
+
+        Err(())
+    }
+}
+
+impl VoprfPublicKey {
+    pub fn create(secret_key: &VoprfSecretKey) -> VoprfPublicKey {
+        VoprfPublicKey {
+            k_big: &secret_key.k * &PUBLIC_PARAMS.g_big,
+        }
+    }
+}
+
+impl VoprfTicket {
+    pub fn create<R>(rng: &mut R) -> Result<(VoprfTicket, VoprfReceipt), ()>
+    where
+        R: RngCore + CryptoRng,
+    {
+        let mut input = [0u8; INPUT_LENGTH];
+        rng.fill_bytes(&mut input);
+        let blind = non_zero_scalar(rng);
+
+        VoprfTicket::create_with_blind(&input, &blind).map(|ticket| {
+            let receipt = VoprfReceipt { input, blind };
+            (ticket, receipt)
+        })
+    }
+
+    // Blind(input, blind)
+    pub fn create_with_blind(input: &[u8], blind: &Scalar) -> Result<VoprfTicket, ()> {
+        let input_element = hash_to_group(input);
+        if input_element == RistrettoPoint::identity() {
+            return Err(());
+        }
+
+        Ok(VoprfTicket {
+            blinded_element: blind * input_element,
+        })
+    }
+}
+
+impl VoprfEvaluation {
+    // BlindEvaluate(skS, pkS, blindedElement)
+    pub fn create<R>(
+        rng: &mut R,
+        sk: &VoprfSecretKey,
+        pk: &VoprfPublicKey,
+        ticket: &VoprfTicket,
+    ) -> VoprfEvaluation
+    where
+        R: RngCore + CryptoRng,
+    {
+        // The proof randomness is hedged from the key and the blinded element
+        let mut nonces = HedgedNonces::with_secrets(
+            rng,
+            b"MacTok-VoprfEvaluation",
+            &[&sk.k],
+            &[&ristretto_bytes(&ticket.blinded_element)],
+        );
+
+        VoprfEvaluation::create_with_nonce(sk, pk, ticket, &nonces.non_zero_scalar())
+    }
+
+    pub fn create_with_nonce(
+        sk: &VoprfSecretKey,
+        pk: &VoprfPublicKey,
+        ticket: &VoprfTicket,
+        r: &Scalar,
+    ) -> VoprfEvaluation {
+        let evaluated_element = sk.k * ticket.blinded_element;
+        let pi = VoprfProof::create(
+            &sk.k,
+            &pk.k_big,
+            &[ticket.blinded_element],
+            &[evaluated_element],
+            r,
+        );
+
+        VoprfEvaluation {
+            evaluated_element,
+            pi,
+        }
+    }
+}
+
+impl VoprfToken {
+    pub fn create(
+        pk: &VoprfPublicKey,
+        ticket: &VoprfTicket,
+        receipt: &VoprfReceipt,
+        evaluation: &VoprfEvaluation,
+    ) -> Result<VoprfToken, ()> {
+        Ok(VoprfToken {
+            input: receipt.input,
+            output: finalize(pk, &receipt.input, &receipt.blind, ticket, evaluation)?,
+        })
+    }
+
+    // Server-side check: re-evaluate the PRF on the input, in constant time
+    pub(crate) fn verify(&self, sk: &VoprfSecretKey) -> bool {
+        let expected = evaluate(sk, &self.input);
+        bool::from(expected.ct_eq(&self.output))
+    }
+}
+
+// Finalize(input, blind, evaluatedElement, blindedElement, pkS, proof)
+pub fn finalize(
+    pk: &VoprfPublicKey,
+    input: &[u8],
+    blind: &Scalar,
+    ticket: &VoprfTicket,
+    evaluation: &VoprfEvaluation,
+) -> Result<[u8; 64], ()> {
+    evaluation.pi.verify(
+        &pk.k_big,
+        &[ticket.blinded_element],
+        &[evaluation.evaluated_element],
+    )?;
+
+    let n_big = blind.invert() * evaluation.evaluated_element;
+
+    Ok(finalize_hash(input, &n_big))
+}
+
+// Evaluate(skS, input), the unblinded PRF
+pub fn evaluate(sk: &VoprfSecretKey, input: &[u8]) -> [u8; 64] {
+    let input_element = hash_to_group(input);
+    finalize_hash(input, &(sk.k * input_element))
+}
+
+impl VoprfProof {
+    // GenerateProof(k, G, pkS, C, D) with proof randomness r
+    pub fn create(
+        k: &Scalar,
+        k_big: &RistrettoPoint,
+        c_big: &[RistrettoPoint],
+        d_big: &[RistrettoPoint],
+        r: &Scalar,
+    ) -> VoprfProof {
+        let (m_big, _) = composites(k_big, c_big, d_big);
+        let z_big = k * m_big;
+
+        let t_big_2 = r * &PUBLIC_PARAMS.g_big;
+        let t_big_3 = r * m_big;
+        let c = proof_challenge(k_big, &m_big, &z_big, &t_big_2, &t_big_3);
+
+        VoprfProof { c, s: r - (c * k) }
+    }
+
+    // VerifyProof(G, pkS, C, D, proof)
+    pub fn verify(
+        &self,
+        k_big: &RistrettoPoint,
+        c_big: &[RistrettoPoint],
+        d_big: &[RistrettoPoint],
+    ) -> Result<(), ()> {
+        if c_big.is_empty() || c_big.len() != d_big.len() {
+            return Err(());
+        }
+
+        let (m_big, z_big) = composites(k_big, c_big, d_big);
+
+        let t_big_2 = (&self.s * &PUBLIC_PARAMS.g_big) + (self.c * k_big);
+        let t_big_3 = (self.s * m_big) + (self.c * z_big);
+        let c = proof_challenge(k_big, &m_big, &z_big, &t_big_2, &t_big_3);
+        if c != self.c {
+            return Err(());
+        }
+
+        Ok(())
+    }
+}
+
+// ComputeComposites: M = sum d_i * C_i and Z = sum d_i * D_i, with weights
+// d_i derived from pkS and each pair (C_i, D_i)
+fn composites(
+    k_big: &RistrettoPoint,
+    c_big: &[RistrettoPoint],
+    d_big: &[RistrettoPoint],
+) -> (RistrettoPoint, RistrettoPoint) {
+    let seed_dst = [b"Seed-".as_ref(), CONTEXT_STRING].concat();
+    let mut seed_transcript = Vec::with_capacity(36 + seed_dst.len());
+    append_length_prefixed(&mut seed_transcript, &ristretto_bytes(k_big));
+    append_length_prefixed(&mut seed_transcript, &seed_dst);
+    let seed = Sha512::digest(&seed_transcript);
+
+    let mut m_big = RistrettoPoint::identity();
+    let mut z_big = RistrettoPoint::identity();
+    for (i, (c_big_i, d_big_i)) in c_big.iter().zip(d_big).enumerate() {
+        let mut transcript = Vec::with_capacity(66 + 2 + 68 + 9);
+        append_length_prefixed(&mut transcript, &seed);
+        transcript.extend_from_slice(&(i as u16).to_be_bytes());
+        append_length_prefixed(&mut transcript, &ristretto_bytes(c_big_i));
+        append_length_prefixed(&mut transcript, &ristretto_bytes(d_big_i));
+        transcript.extend_from_slice(b"Composite");
+
+        let d_i = hash_to_scalar(&transcript);
+        m_big += d_i * c_big_i;
+        z_big += d_i * d_big_i;
+    }
+
+    (m_big, z_big)
+}
+
+fn proof_challenge(
+    k_big: &RistrettoPoint,
+    m_big: &RistrettoPoint,
+    z_big: &RistrettoPoint,
+    t_big_2: &RistrettoPoint,
+    t_big_3: &RistrettoPoint,
+) -> Scalar {
+    let mut transcript = Vec::with_capacity(5 * 34 + 9);
+    for point in [k_big, m_big, z_big, t_big_2, t_big_3] {
+        append_length_prefixed(&mut transcript, &ristretto_bytes(point));
+    }
+    transcript.extend_from_slice(b"Challenge");
+
+    hash_to_scalar(&transcript)
+}
+
+fn finalize_hash(input: &[u8], n_big: &RistrettoPoint) -> [u8; 64] {
+    let mut hasher = Sha512::new();
+    hasher.update((input.len() as u16).to_be_bytes());
+    hasher.update(input);
+    hasher.update(32u16.to_be_bytes());
+    hasher.update(ristretto_bytes(n_big));
+    hasher.update(b"Finalize");
+
+    hasher
+        .finalize()
+        .as_slice()
+        .try_into()
+        .expect("incorrect size for hash")
+}
+
+fn append_length_prefixed(transcript: &mut Vec<u8>, bytes: &[u8]) {
+    transcript.extend_from_slice(&(bytes.len() as u16).to_be_bytes());
+    transcript.extend_from_slice(bytes);
+}
+
+// HashToGroup: hash_to_ristretto255 with expand_message_xmd (RFC 9380)
+pub fn hash_to_group(input: &[u8]) -> RistrettoPoint {
+    let dst = [b"HashToGroup-".as_ref(), CONTEXT_STRING].concat();
+    let mut uniform_bytes = [0u8; 64];
+    expand_message_xmd(input, &dst, &mut uniform_bytes);
+
+    RistrettoPoint::from_uniform_bytes(&uniform_bytes)
+}
+
+pub fn hash_to_scalar(input: &[u8]) -> Scalar {
+    let dst = [b"HashToScalar-".as_ref(), CONTEXT_STRING].concat();
+    hash_to_scalar_with_dst(input, &dst)
+}
+
+fn hash_to_scalar_with_dst(input: &[u8], dst: &[u8]) -> Scalar {
+    let mut uniform_bytes = [0u8; 64];
+    expand_message_xmd(input, dst, &mut uniform_bytes);
+
+    Scalar::from_bytes_mod_order_wide(&uniform_bytes)
+}
+
+// expand_message_xmd with SHA-512 (RFC 9380, Section 5.3.1). Outputs are at
+// most 255 * 64 bytes and DSTs at most 255 bytes, which covers every use here.
+pub(crate) fn expand_message_xmd(msg: &[u8], dst: &[u8], output: &mut [u8]) {
+    let ell = output.len().div_ceil(64);
+    assert!(ell <= 255 && output.len() <= u16::MAX as usize && dst.len() <= 255);
+
+    let dst_prime = [dst, &[dst.len() as u8]].concat();
+
+    let mut hasher = Sha512::new();
+    hasher.update([0u8; 128]);
+    hasher.update(msg);
+    hasher.update((output.len() as u16).to_be_bytes());
+    hasher.update([0u8]);
+    hasher.update(&dst_prime);
+    let b_0 = hasher.finalize();
+
+    let mut b_i = Sha512::new()
+        .chain(b_0)
+        .chain([1u8])
+        .chain(&dst_prime)
+        .finalize();
+    for (i, chunk) in output.chunks_mut(64).enumerate() {
+        if i > 0 {
+            let mut xored = [0u8; 64];
+            for (x, (a, b)) in xored.iter_mut().zip(b_0.iter().zip(b_i.iter())) {
+                *x = a ^ b;
+            }
+            b_i = Sha512::new()
+                .chain(xored)
+                .chain([(i + 1) as u8])
+                .chain(&dst_prime)
+                .finalize();
+        }
+        chunk.copy_from_slice(&b_i[..chunk.len()]);
+    }
+}